@@ -1,5 +1,12 @@
-use crate::value::Value;
-use std::{collections::HashMap, convert::TryFrom};
+use crate::{
+    intrinsic::Intrinsic,
+    value::{ArithmeticMode, TypeTag, Value},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    mem::size_of,
+};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Instruction {
@@ -9,26 +16,304 @@ pub enum Instruction {
     Multiply,
     Divide,
     Modulo,
+    Power,
     ShiftLeft,
     ShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
     Negate,
     Call(u16),
+    /// Calls the subroutine at the given offset in place of the current one, which returns
+    /// whatever the callee returns.
+    TailCall(u16),
     True,
     False,
     Not,
+    BitNot,
+    Dereference,
     Equal,
     Greater,
     Less,
     GetVar(u16),
     GetOrCreateVar(u16),
+    Reference(u16),
     Assign,
     JumpIf(u16),
     Goto(u16),
     Print(bool),
+    PrintWidth,
+    Inspect,
     Constant(u16),
+    Intrinsic(Intrinsic),
+    Clear(u16),
+    Checksum(u16),
+    Index(u16),
+    IndexAddr(u16),
+    Scan(TypeTag),
+    /// Converts the value on top of the stack to the given type.
+    Cast(TypeTag),
+    /// Returns from the subroutine, handing the value on top of the stack to the caller.
+    ReturnValue,
+    /// Pushes the value handed back by the last subroutine that returned.
+    PushResult,
+    Pop,
+    /// Pushes a copy of the value on top of the stack.
+    Dup,
+    /// Exchanges the two values on top of the stack.
+    Swap,
     Halt,
 }
 
+impl Instruction {
+    /// Applies the binary operator of this instruction to two values as the VM would, `None` if
+    /// the instruction is not a binary operator.
+    pub fn apply_binary(self, a: Value, b: Value) -> Option<Result<Value, String>> {
+        let result = match self {
+            Instruction::Add => a + b,
+            Instruction::Subtract => a - b,
+            Instruction::Multiply => a * b,
+            Instruction::Divide => a / b,
+            Instruction::Modulo => a % b,
+            Instruction::Power => Value::pow(a, b),
+            Instruction::ShiftLeft => a << b,
+            Instruction::ShiftRight => a >> b,
+            Instruction::BitAnd => a & b,
+            Instruction::BitOr => a | b,
+            Instruction::BitXor => a ^ b,
+            Instruction::Equal => Value::eq(a, b),
+            Instruction::Greater => Value::gt(a, b),
+            Instruction::Less => Value::lt(a, b),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// Like `apply_binary`, with integer operations whose exact result does not fit in the type
+    /// of their operands behaving as `mode` says.
+    pub fn apply_arithmetic(
+        self,
+        a: Value,
+        b: Value,
+        mode: ArithmeticMode,
+    ) -> Option<Result<Value, String>> {
+        match mode {
+            ArithmeticMode::Wrap => self.apply_binary(a, b),
+            ArithmeticMode::Checked => self.apply_checked(a, b),
+            ArithmeticMode::Saturate => self.apply_saturating(a, b),
+        }
+    }
+
+    /// Like `apply_binary`, but integer operations whose exact result does not fit in the type of
+    /// their operands are an error instead of wrapping around.
+    pub fn apply_checked(self, a: Value, b: Value) -> Option<Result<Value, String>> {
+        let result = self.apply_binary(a, b)?;
+        let (a, b) = self.promote(a, b);
+
+        if result.is_ok() && self.overflows(a, b) {
+            let symbol = match self {
+                Instruction::Add => "+",
+                Instruction::Subtract => "-",
+                Instruction::Multiply => "*",
+                Instruction::Divide => "/",
+                _ => "**",
+            };
+
+            return Some(Err(format!(
+                "Integer overflow, the result of {} {} {} does not fit in {}",
+                a,
+                symbol,
+                b,
+                a.type_info()
+            )));
+        }
+
+        Some(result)
+    }
+
+    /// Like `apply_binary`, but integer operations whose exact result does not fit in the type of
+    /// their operands give the smallest or largest value of the type instead of wrapping around.
+    pub fn apply_saturating(self, a: Value, b: Value) -> Option<Result<Value, String>> {
+        let result = self.apply_binary(a, b)?;
+        let (a, b) = self.promote(a, b);
+
+        if result.is_err() || !self.overflows(a, b) {
+            return Some(result);
+        }
+
+        // exponents too big for pow overflow anyway, only their parity matters for the sign
+        let exponent = |b: u64| u32::try_from(b).unwrap_or(u32::MAX - 1 + (b & 1) as u32);
+
+        let saturated = match (self, a, b) {
+            (Instruction::Add, Value::U64(a), Value::U64(b)) => Value::U64(a.saturating_add(b)),
+            (Instruction::Add, Value::I64(a), Value::I64(b)) => Value::I64(a.saturating_add(b)),
+            (Instruction::Subtract, Value::U64(a), Value::U64(b)) => {
+                Value::U64(a.saturating_sub(b))
+            }
+            (Instruction::Subtract, Value::I64(a), Value::I64(b)) => {
+                Value::I64(a.saturating_sub(b))
+            }
+            (Instruction::Multiply, Value::U64(a), Value::U64(b)) => {
+                Value::U64(a.saturating_mul(b))
+            }
+            (Instruction::Multiply, Value::I64(a), Value::I64(b)) => {
+                Value::I64(a.saturating_mul(b))
+            }
+            (Instruction::Divide, Value::I64(a), Value::I64(b)) => Value::I64(a.saturating_div(b)),
+            (Instruction::Power, Value::U64(a), Value::U64(b)) => {
+                Value::U64(a.saturating_pow(exponent(b)))
+            }
+            (Instruction::Power, Value::I64(a), Value::I64(b)) => {
+                Value::I64(a.saturating_pow(exponent(b as u64)))
+            }
+            _ => panic!("Overflowing operation can not saturate"),
+        };
+
+        Some(Ok(saturated))
+    }
+
+    /// Operands converted to the common type the operator applies to them, see `Value::promote`.
+    pub fn promote(self, a: Value, b: Value) -> (Value, Value) {
+        match self {
+            Instruction::Power => (a, b),
+            _ => Value::promote(a, b).unwrap_or((a, b)),
+        }
+    }
+
+    /// Whether an integer operation between two values wraps around instead of giving the exact
+    /// result, once they are promoted to a common type.
+    pub fn overflows(self, a: Value, b: Value) -> bool {
+        let (a, b) = self.promote(a, b);
+
+        match (self, a, b) {
+            (Instruction::Add, Value::U64(a), Value::U64(b)) => a.checked_add(b).is_none(),
+            (Instruction::Add, Value::I64(a), Value::I64(b)) => a.checked_add(b).is_none(),
+            (Instruction::Subtract, Value::U64(a), Value::U64(b)) => a.checked_sub(b).is_none(),
+            (Instruction::Subtract, Value::I64(a), Value::I64(b)) => a.checked_sub(b).is_none(),
+            (Instruction::Multiply, Value::U64(a), Value::U64(b)) => a.checked_mul(b).is_none(),
+            (Instruction::Multiply, Value::I64(a), Value::I64(b)) => a.checked_mul(b).is_none(),
+            (Instruction::Divide, Value::I64(a), Value::I64(b)) => a == i64::MIN && b == -1,
+            (Instruction::Power, Value::U64(a), Value::U64(b)) if a > 1 => u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .is_none(),
+            (Instruction::Power, Value::I64(a), Value::I64(b)) if !(-1..=1).contains(&a) => {
+                u32::try_from(b)
+                    .ok()
+                    .and_then(|b| a.checked_pow(b))
+                    .is_none()
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of values popped from and pushed onto the stack by this instruction. The values
+    /// consumed by a call as parameters depend on the count known only at runtime and are not
+    /// included.
+    pub fn stack_effect(&self) -> (usize, usize) {
+        match self {
+            Instruction::Return
+            | Instruction::Goto(_)
+            | Instruction::Clear(_)
+            | Instruction::Halt => (0, 0),
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Modulo
+            | Instruction::Power
+            | Instruction::ShiftLeft
+            | Instruction::ShiftRight
+            | Instruction::BitAnd
+            | Instruction::BitOr
+            | Instruction::BitXor
+            | Instruction::Equal
+            | Instruction::Greater
+            | Instruction::Less => (2, 1),
+            Instruction::Negate
+            | Instruction::Not
+            | Instruction::BitNot
+            | Instruction::Dereference
+            | Instruction::Index(_)
+            | Instruction::IndexAddr(_)
+            | Instruction::Cast(_) => (1, 1),
+            Instruction::Call(_)
+            | Instruction::TailCall(_)
+            | Instruction::JumpIf(_)
+            | Instruction::Print(_)
+            | Instruction::Inspect
+            | Instruction::ReturnValue
+            | Instruction::Pop => (1, 0),
+            Instruction::True
+            | Instruction::False
+            | Instruction::GetVar(_)
+            | Instruction::GetOrCreateVar(_)
+            | Instruction::Reference(_)
+            | Instruction::Constant(_)
+            | Instruction::Checksum(_)
+            | Instruction::Scan(_)
+            | Instruction::PushResult => (0, 1),
+            Instruction::Dup => (1, 2),
+            Instruction::Swap => (2, 2),
+            Instruction::Assign | Instruction::PrintWidth => (2, 0),
+            Instruction::Intrinsic(intrinsic) => (intrinsic.arity(), 1),
+        }
+    }
+}
+
+/// Whether the instructions from `start` to the end of the code never pop more values than they
+/// pushed and leave the stack as they found it. Forward gotos are followed, so that both branches
+/// of a sequence that pushes one of two values are not counted twice. Calls are barriers: the
+/// values pushed before them are taken to be their parameters, which they consume.
+pub fn is_balanced(code: &[Instruction], start: usize) -> bool {
+    let mut depth = 0usize;
+    let mut ip = start;
+
+    while let Some(instruction) = code.get(ip) {
+        let (pops, pushes) = instruction.stack_effect();
+        depth = match depth.checked_sub(pops) {
+            Some(_) if matches!(instruction, Instruction::Call(_) | Instruction::TailCall(_)) => 0,
+            Some(depth) => depth + pushes,
+            None => return false,
+        };
+
+        ip = match instruction {
+            Instruction::Goto(target) if usize::from(*target) > ip => usize::from(*target),
+            _ => ip + 1,
+        };
+    }
+
+    depth == 0
+}
+
+/// Whether the subroutine starting at `entry` calls itself on every path before it can return.
+/// Only straight-line code and unconditional jumps are followed, so any conditional branch or call
+/// to another subroutine is assumed to possibly end the recursion.
+pub fn recurses_endlessly(code: &[Instruction], entry: usize) -> bool {
+    let mut visited = HashSet::new();
+    let mut ip = entry;
+
+    while visited.insert(ip) {
+        match code.get(ip) {
+            Some(Instruction::Call(target) | Instruction::TailCall(target)) => {
+                return usize::from(*target) == entry
+            }
+            Some(Instruction::Goto(target)) => ip = usize::from(*target),
+            Some(
+                Instruction::JumpIf(_)
+                | Instruction::Return
+                | Instruction::ReturnValue
+                | Instruction::Halt,
+            )
+            | None => return false,
+            Some(_) => ip += 1,
+        }
+    }
+
+    false
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct LineStart {
     offset: usize,
@@ -41,13 +326,51 @@ impl LineStart {
     }
 }
 
-#[derive(Default)]
+/// Sizes of the parts of a chunk at some point, to take it back there with `Chunk::truncate`.
+#[derive(Copy, Clone, Debug)]
+pub struct Mark {
+    code: usize,
+    data: usize,
+    labels: usize,
+    constants: usize,
+    names: usize,
+    strings: usize,
+    lines: usize,
+    source_lines: usize,
+}
+
+/// Array of values declared in the data section, laid out in consecutive stack slots before the
+/// program runs. The name is a variable holding the address of the first value, unless the
+/// declaration is a lone value, which the variable holds itself.
+#[derive(Clone)]
+pub struct DataArray {
+    pub name: u16,
+    pub values: Vec<Value>,
+    pub scalar: bool,
+}
+
+#[derive(Clone, Default)]
 pub struct Chunk {
     pub code: Vec<Instruction>,
+    pub data: Vec<DataArray>,
+    /// Name and offset of every label, sorted by offset, for debugging output.
+    pub labels: Vec<(String, usize)>,
+    /// Whether the compiler fills `labels`, which are only needed when disassembling.
+    pub keep_labels: bool,
     constants: Vec<Value>,
+    /// Index of each constant keyed by `Value::tag_and_bits`. Floats are compared by their bits,
+    /// so `0.0` and `-0.0` are kept apart as they print differently.
+    constants_rev: HashMap<(u8, u64), u16>,
     names: Vec<String>,
     names_rev: HashMap<String, u16>,
+    /// Text of the string literals, interned so that equal strings share an id. The empty string
+    /// is not stored and always has id 0.
+    strings: Vec<String>,
+    strings_rev: HashMap<String, u16>,
     lines: Vec<LineStart>,
+    /// Index in `lines` of the first line of the source being compiled, lines of different
+    /// sources appended to the same chunk are not ordered among themselves.
+    source_lines: usize,
 }
 
 impl Chunk {
@@ -55,8 +378,23 @@ impl Chunk {
         Self::default()
     }
 
+    /// Names of the labels placed just before the instruction at `offset`.
+    pub fn labels_at(&self, offset: usize) -> impl Iterator<Item = &str> {
+        let start = self.labels.partition_point(|(_, label)| *label < offset);
+
+        self.labels[start..]
+            .iter()
+            .take_while(move |(_, label)| *label == offset)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Starts the code of a new source, whose lines restart from the first.
+    pub fn begin_source(&mut self) {
+        self.source_lines = self.lines.len();
+    }
+
     pub fn write(&mut self, i: Instruction, line: usize) -> usize {
-        if let Some(line_start) = self.lines.last() {
+        if let Some(line_start) = self.lines[self.source_lines..].last() {
             assert!(
                 line_start.line <= line,
                 "Line of new instruction cannot be smaller than previous instruction"
@@ -67,7 +405,7 @@ impl Chunk {
 
         self.code.push(i);
 
-        match self.lines.last() {
+        match self.lines[self.source_lines..].last() {
             Some(cur_line) if cur_line.line == line => {}
             _ => self.lines.push(LineStart::new(index, line)),
         };
@@ -75,18 +413,78 @@ impl Chunk {
         index
     }
 
+    /// Removes the instructions whose entry in `keep` is false, moving the targets of jumps, the
+    /// labels and the line information along with the remaining instructions. Jump targets must
+    /// be kept.
+    pub fn retain_code(&mut self, keep: &[bool]) {
+        // new offset of each instruction, and of the end of the code
+        let mut offsets = Vec::with_capacity(self.code.len() + 1);
+        let mut next = 0;
+        for kept in keep {
+            offsets.push(next);
+            next += usize::from(*kept);
+        }
+        offsets.push(next);
+
+        let mut code = Vec::with_capacity(next);
+        let mut lines: Vec<LineStart> = vec![];
+
+        for (idx, instruction) in self.code.iter().enumerate() {
+            if !keep[idx] {
+                continue;
+            }
+
+            let moved = |target: u16| offsets[usize::from(target)] as u16;
+            code.push(match *instruction {
+                Instruction::Goto(target) => Instruction::Goto(moved(target)),
+                Instruction::JumpIf(target) => Instruction::JumpIf(moved(target)),
+                Instruction::Call(target) => Instruction::Call(moved(target)),
+                Instruction::TailCall(target) => Instruction::TailCall(moved(target)),
+                instruction => instruction,
+            });
+
+            let line = self.get_line(idx);
+            if lines.last().map(|start| start.line) != Some(line) {
+                lines.push(LineStart::new(code.len() - 1, line));
+            }
+        }
+
+        for (_, offset) in &mut self.labels {
+            *offset = offsets[*offset];
+        }
+
+        self.code = code;
+        self.lines = lines;
+        // lines of the sources are merged, so code written afterwards starts a new one
+        self.source_lines = self.lines.len();
+    }
+
+    /// Adds a constant to the pool, reusing the index of an identical constant if there is one.
     pub fn add_constant(&mut self, value: Value) -> Result<u16, &'static str> {
+        let key = value.tag_and_bits();
+        if let Some(index) = self.constants_rev.get(&key) {
+            return Ok(*index);
+        }
+
         let index = self.constants.len();
 
         match u16::try_from(index) {
             Ok(index) => {
                 self.constants.push(value);
+                self.constants_rev.insert(key, index);
                 Ok(index)
             }
             Err(_) => Err("Could not add constant, reached limit of u16 max size"),
         }
     }
 
+    /// Removes the constant added last to the pool, which must no longer be used by any code.
+    pub fn pop_constant(&mut self) {
+        if let Some(value) = self.constants.pop() {
+            self.constants_rev.remove(&value.tag_and_bits());
+        }
+    }
+
     pub fn get_constant(&self, addr: u16) -> Value {
         let idx = usize::from(addr);
         self.constants
@@ -95,6 +493,83 @@ impl Chunk {
             .expect("Could not get constant")
     }
 
+    /// Current size of the chunk, see `truncate`.
+    pub fn mark(&self) -> Mark {
+        Mark {
+            code: self.code.len(),
+            data: self.data.len(),
+            labels: self.labels.len(),
+            constants: self.constants.len(),
+            names: self.names.len(),
+            strings: self.strings.len(),
+            lines: self.lines.len(),
+            source_lines: self.source_lines,
+        }
+    }
+
+    /// Removes everything added to the chunk since `mark` was taken.
+    pub fn truncate(&mut self, mark: Mark) {
+        self.code.truncate(mark.code);
+        self.data.truncate(mark.data);
+        self.labels.truncate(mark.labels);
+        self.constants.truncate(mark.constants);
+        self.constants_rev
+            .retain(|_, index| usize::from(*index) < mark.constants);
+        self.names.truncate(mark.names);
+        self.names_rev
+            .retain(|_, index| usize::from(*index) < mark.names);
+        self.strings.truncate(mark.strings);
+        // string ids start at 1, 0 being the empty string
+        self.strings_rev
+            .retain(|_, id| usize::from(*id) <= mark.strings);
+        self.lines.truncate(mark.lines);
+        self.source_lines = mark.source_lines;
+    }
+
+    pub fn constants_len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn names_len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Number of bytes taken by the instructions, constants, names and data arrays.
+    pub fn byte_size(&self) -> usize {
+        let code = self.code.len() * size_of::<Instruction>();
+        let constants = self.constants.len() * size_of::<Value>();
+        let names: usize = self.names.iter().map(String::len).sum();
+        let strings: usize = self.strings.iter().map(String::len).sum();
+        let data: usize = self
+            .data
+            .iter()
+            .map(|array| size_of::<u16>() + array.values.len() * size_of::<Value>())
+            .sum();
+
+        code + constants + names + strings + data
+    }
+
+    pub fn add_data(&mut self, name: u16, values: Vec<Value>) {
+        self.data.push(DataArray {
+            name,
+            values,
+            scalar: false,
+        });
+    }
+
+    /// Declares a data variable initialized to a lone value instead of an array.
+    pub fn add_data_value(&mut self, name: u16, value: Value) {
+        self.data.push(DataArray {
+            name,
+            values: vec![value],
+            scalar: true,
+        });
+    }
+
+    pub fn get_data(&self, name: u16) -> Option<&DataArray> {
+        self.data.iter().find(|array| array.name == name)
+    }
+
     pub fn add_name(&mut self, name: &str) -> Result<u16, &'static str> {
         if let Some(addr) = self.names_rev.get(name) {
             return Ok(*addr);
@@ -112,6 +587,42 @@ impl Chunk {
         }
     }
 
+    pub fn add_string(&mut self, text: &str) -> Result<u16, &'static str> {
+        if text.is_empty() {
+            return Ok(0);
+        }
+        if let Some(id) = self.strings_rev.get(text) {
+            return Ok(*id);
+        }
+
+        match u16::try_from(self.strings.len() + 1) {
+            Ok(id) => {
+                self.strings.push(text.to_string());
+                self.strings_rev.insert(text.to_string(), id);
+                Ok(id)
+            }
+            Err(_) => Err("Could not add string, reached limit of u16 max size"),
+        }
+    }
+
+    /// Formats a value as `Display` does, resolving the text of strings, which only the chunk
+    /// knows. The alternate form quotes strings and annotates every value with its type.
+    pub fn display_value(&self, value: Value, alternate: bool) -> String {
+        match value {
+            Value::Str(id) if alternate => format!("{:?}:str", self.get_string(id)),
+            Value::Str(id) => self.get_string(id).to_string(),
+            value if alternate => format!("{:#}", value),
+            value => value.to_string(),
+        }
+    }
+
+    pub fn get_string(&self, id: u16) -> &str {
+        match usize::from(id).checked_sub(1) {
+            Some(idx) => self.strings.get(idx).expect("Could not get string"),
+            None => "",
+        }
+    }
+
     pub fn get_name(&self, addr: u16) -> &str {
         let idx = usize::from(addr);
         self.names.get(idx).expect("Could not get name")
@@ -145,11 +656,9 @@ impl Chunk {
                 Some(mid_line) => {
                     if instruction_idx >= mid_line.offset {
                         line = mid_line.line;
-
-                        if mid == 0 {
-                            break;
-                        }
                         left = mid + 1;
+                    } else if mid == 0 {
+                        break;
                     } else {
                         right = mid - 1;
                     }
@@ -166,7 +675,22 @@ impl Chunk {
 mod test {
     use std::mem::size_of;
 
-    use crate::chunk::Instruction;
+    use crate::{
+        chunk::{is_balanced, recurses_endlessly, Chunk, Instruction},
+        compiler::Compiler,
+        intrinsic::Intrinsic,
+        value::{ArithmeticMode, Value},
+    };
+
+    #[test]
+    fn displayed_strings_show_their_text() {
+        let mut chunk = Chunk::new();
+        let id = chunk.add_string("hi").unwrap();
+
+        assert_eq!(chunk.display_value(Value::Str(id), false), "hi");
+        assert_eq!(chunk.display_value(Value::Str(id), true), "\"hi\":str");
+        assert_eq!(chunk.display_value(Value::I64(-5), true), "-5i64");
+    }
 
     #[test]
     fn instruction_is_at_most_64_bits() {
@@ -174,4 +698,170 @@ mod test {
         // variant
         assert!(size_of::<Instruction>() <= 4);
     }
+
+    #[test]
+    fn complete_statements_are_balanced() {
+        assert!(is_balanced(
+            &[
+                Instruction::GetOrCreateVar(0),
+                Instruction::Constant(0),
+                Instruction::Constant(1),
+                Instruction::Add,
+                Instruction::Assign,
+            ],
+            0
+        ));
+        assert!(is_balanced(
+            &[
+                Instruction::GetVar(0),
+                Instruction::Intrinsic(Intrinsic::ToUpper),
+                Instruction::Print(true),
+                Instruction::Halt,
+            ],
+            0
+        ));
+        // x = a || b, where only one of the two booleans is pushed
+        assert!(is_balanced(
+            &[
+                Instruction::Halt,
+                Instruction::GetOrCreateVar(0),
+                Instruction::GetVar(1),
+                Instruction::JumpIf(8),
+                Instruction::GetVar(2),
+                Instruction::JumpIf(8),
+                Instruction::False,
+                Instruction::Goto(9),
+                Instruction::True,
+                Instruction::Assign,
+            ],
+            1
+        ));
+    }
+
+    #[test]
+    fn calls_consume_the_parameters_pushed_before_them() {
+        let source = "\
+param 1
+param 2
+call add, 2
+x = call add 2
+return call add 2
+add:
+return
+";
+        let mut chunk = Chunk::new();
+        Compiler::compile(source, &mut chunk, true).unwrap();
+
+        assert!(is_balanced(&chunk.code, 0));
+    }
+
+    #[test]
+    fn truncated_statements_are_not_balanced() {
+        // assignment missing its final Assign
+        assert!(!is_balanced(
+            &[Instruction::GetOrCreateVar(0), Instruction::Constant(0)],
+            0
+        ));
+        // binary operation missing its left operand
+        assert!(!is_balanced(
+            &[
+                Instruction::Constant(0),
+                Instruction::Add,
+                Instruction::Print(false),
+            ],
+            0
+        ));
+    }
+
+    #[test]
+    fn unconditional_self_call_recurses_endlessly() {
+        let code = [
+            Instruction::Constant(0),
+            Instruction::Call(3),
+            Instruction::Halt,
+            Instruction::GetOrCreateVar(0),
+            Instruction::Constant(1),
+            Instruction::Assign,
+            Instruction::Constant(0),
+            Instruction::Call(3),
+            Instruction::Return,
+        ];
+        assert!(recurses_endlessly(&code, 3));
+        assert!(!recurses_endlessly(&code, 0));
+    }
+
+    #[test]
+    fn branches_and_returns_may_end_recursion() {
+        let with_base_case = [
+            Instruction::GetVar(0),
+            Instruction::JumpIf(4),
+            Instruction::Constant(0),
+            Instruction::Call(0),
+            Instruction::Return,
+        ];
+        assert!(!recurses_endlessly(&with_base_case, 0));
+
+        let returns_first = [Instruction::Return, Instruction::Call(0)];
+        assert!(!recurses_endlessly(&returns_first, 0));
+
+        let loops_forever = [Instruction::Goto(0)];
+        assert!(!recurses_endlessly(&loops_forever, 0));
+    }
+
+    #[test]
+    fn line_of_instruction_at_start_of_line() {
+        let mut chunk = Chunk::new();
+        for (i, line) in [2, 2, 2, 3, 3, 4, 5, 5, 6].iter().enumerate() {
+            chunk.write(Instruction::Constant(i as u16), *line);
+        }
+
+        let lines: Vec<usize> = (0..9).map(|idx| chunk.get_line(idx)).collect();
+        assert_eq!(lines, vec![2, 2, 2, 3, 3, 4, 5, 5, 6]);
+    }
+
+    #[test]
+    fn strings_are_interned() {
+        let mut chunk = Chunk::new();
+
+        assert_eq!(chunk.add_string(""), Ok(0));
+        assert_eq!(chunk.add_string("hello"), Ok(1));
+        assert_eq!(chunk.add_string("world"), Ok(2));
+        assert_eq!(chunk.add_string("hello"), Ok(1));
+        assert_eq!(chunk.get_string(0), "");
+        assert_eq!(chunk.get_string(2), "world");
+    }
+
+    #[test]
+    fn identical_constants_share_an_index() {
+        let mut chunk = Chunk::new();
+
+        assert_eq!(chunk.add_constant(Value::I64(7)), Ok(0));
+        assert_eq!(chunk.add_constant(Value::I64(7)), Ok(0));
+        assert_eq!(chunk.constants_len(), 1);
+
+        assert_eq!(chunk.add_constant(Value::U64(7)), Ok(1));
+        assert_eq!(chunk.add_constant(Value::F64(0.0)), Ok(2));
+        assert_eq!(chunk.add_constant(Value::F64(-0.0)), Ok(3));
+        assert_eq!(chunk.add_constant(Value::F64(f64::NAN)), Ok(4));
+        assert_eq!(chunk.add_constant(Value::F64(f64::NAN)), Ok(4));
+        assert_eq!(chunk.constants_len(), 5);
+    }
+
+    #[test]
+    fn mixed_operands_overflow_as_their_common_type() {
+        let (a, b) = (Value::I64(i64::MAX), Value::U64(1));
+
+        assert!(matches!(
+            Instruction::Add.apply_arithmetic(a, b, ArithmeticMode::Wrap),
+            Some(Ok(Value::I64(i64::MIN)))
+        ));
+        assert!(matches!(
+            Instruction::Add.apply_arithmetic(a, b, ArithmeticMode::Checked),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            Instruction::Add.apply_arithmetic(a, b, ArithmeticMode::Saturate),
+            Some(Ok(Value::I64(i64::MAX)))
+        ));
+    }
 }
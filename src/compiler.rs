@@ -1,27 +1,65 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
-    chunk::{Chunk, Instruction},
-    error::{error_at, TACError, TACResult},
-    scanner::Scanner,
+    chunk::{is_balanced, recurses_endlessly, Chunk, Instruction},
+    error::{did_you_mean, error_at, warning, TACError, TACResult},
+    intrinsic::Intrinsic,
+    scanner::{unescape, Scanner},
     token::{Token, TokenKind},
-    value::Value,
+    value::{TypeTag, Value},
 };
 
+const NEGATIVE_U64: &str = "It is not possible to negate a number of type u64";
+
 pub struct Compiler<'source, 'c> {
+    source: &'source str,
     scanner: Scanner<'source>,
     chunk: &'c mut Chunk,
     had_error: bool,
     panic_mode: bool,
     current: Token<'source>,
     previous: Token<'source>,
-    labels: HashMap<&'source str, usize>,
+    /// Offset of the code and line of each label definition.
+    labels: HashMap<&'source str, (usize, usize)>,
     pending_labels: HashMap<&'source str, Vec<(usize, usize)>>,
+    /// Offset of the first instruction of the last statement that emitted any code.
+    last_statement: usize,
+    /// Offset of the first instruction of this source in the chunk.
+    code_start: usize,
+    /// Variables assigned so far, including the ones of sources compiled before into the chunk.
+    assigned: HashSet<u16>,
+    /// Reads of variables not assigned on any previous line, reported at the end unless they
+    /// turn out to be data arrays, which may be declared anywhere.
+    unassigned_reads: Vec<(u16, Token<'source>)>,
+    /// Offsets of the last two `Constant` instructions whose value was added to the pool by them,
+    /// so that folding can take the values of its operands back out.
+    new_constants: [Option<usize>; 2],
+    implicit_halt: bool,
 }
 
 impl<'source, 'c> Compiler<'source, 'c> {
-    pub fn compile(source: &'source str, chunk: &'c mut Chunk) -> TACResult<()> {
+    /// Compiles the source, appending its code to the chunk. When `implicit_halt` is false, no
+    /// `Halt` is added after the last statement, so execution falls through to whatever code is
+    /// compiled next into the same chunk. If nothing follows, execution reaches the end of the
+    /// code and fails with a runtime error.
+    pub fn compile(
+        source: &'source str,
+        chunk: &'c mut Chunk,
+        implicit_halt: bool,
+    ) -> TACResult<()> {
+        chunk.begin_source();
+
+        let last_statement = chunk.code.len();
+        let assigned = chunk
+            .code
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::GetOrCreateVar(name) => Some(*name),
+                _ => None,
+            })
+            .collect();
         let mut compiler = Self {
+            source,
             scanner: Scanner::new(source),
             chunk,
             had_error: false,
@@ -30,12 +68,22 @@ impl<'source, 'c> Compiler<'source, 'c> {
             previous: Token::synthetic(""),
             labels: HashMap::new(),
             pending_labels: HashMap::new(),
+            last_statement,
+            code_start: last_statement,
+            assigned,
+            unassigned_reads: vec![],
+            new_constants: [None; 2],
+            implicit_halt,
         };
 
         compiler.advance();
 
         while !compiler.match_advance(TokenKind::Eof) {
+            let start = compiler.chunk.code.len();
             compiler.statement();
+            if compiler.chunk.code.len() > start {
+                compiler.last_statement = start;
+            }
 
             if compiler.panic_mode {
                 compiler.synchronize();
@@ -66,17 +114,30 @@ impl<'source, 'c> Compiler<'source, 'c> {
 
         match self.previous.kind {
             TokenKind::Print | TokenKind::PrintLn => self.print_statement(),
+            TokenKind::PrintW => self.print_width_statement(),
+            TokenKind::Inspect => self.inspect_statement(),
             TokenKind::If | TokenKind::IfFalse => self.if_statement(),
             TokenKind::Goto => self.goto_statement(),
             TokenKind::Halt => self.emit_instruction(Instruction::Halt),
             TokenKind::Call => self.call_statement(),
-            TokenKind::Return => self.emit_instruction(Instruction::Return),
+            TokenKind::Param => self.operand(),
+            TokenKind::Return => self.return_statement(),
+            TokenKind::Clear => self.clear_statement(),
+            TokenKind::Intrinsic => self.discarded_intrinsic(),
             TokenKind::Star => self.assignment(),
             TokenKind::Identifier => self.label_or_assignment(),
+            TokenKind::Dot => self.data_declaration(),
+            TokenKind::Number | TokenKind::Char | TokenKind::True | TokenKind::False
+                if self.current.kind.is_comparison() =>
+            {
+                self.discarded_comparison()
+            }
 
             TokenKind::NewLine | TokenKind::Eof => return,
             TokenKind::Equal => self.error("Assignments must have a variable on the left side"),
-            TokenKind::Scan => self.error("Return value of scan must be assigned to a variable"),
+            TokenKind::Scan => {
+                self.error("Return value of scan must be assigned to a variable, as in 'x = scan'")
+            }
             k => self.error(&format!("Invalid statement with token {:?}", k)),
         }
 
@@ -91,31 +152,143 @@ impl<'source, 'c> Compiler<'source, 'c> {
             | TokenKind::Minus
             | TokenKind::Plus
             | TokenKind::Star
+            | TokenKind::StarStar
+            | TokenKind::AmpAmp
+            | TokenKind::PipePipe
             | TokenKind::Slash
             | TokenKind::Percent
             | TokenKind::ShiftLeft
             | TokenKind::ShiftRight
             | TokenKind::Bang
-            | TokenKind::Ampersand => self
+            | TokenKind::Tilde
+            | TokenKind::Ampersand
+            | TokenKind::Pipe
+            | TokenKind::Caret => self
                 .error_at_current("Three-address code programs support at most binary expressions"),
             _ => self.error_at_current("There must be at most one statement per line"),
         }
     }
 
+    fn data_declaration(&mut self) {
+        if !self.check(TokenKind::Identifier) || self.current.lexeme != "data" {
+            return self.error_at_current("Expected 'data' directive after '.'");
+        }
+        self.advance();
+
+        self.consume(TokenKind::Identifier, "Missing name of data declaration");
+        let name = self.previous;
+
+        let name_addr = match self.chunk.add_name(name.lexeme) {
+            Ok(addr) => addr,
+            Err(_) => return self.error("The program uses too many variables (65535+)"),
+        };
+
+        if self.chunk.get_data(name_addr).is_some() {
+            return self.error_at(name, "Redefinition of data declarations is not allowed");
+        }
+
+        self.consume(TokenKind::Equal, "Missing '=' in data declaration");
+
+        // a lone string is a variable holding it rather than an array
+        if self.check(TokenKind::String) {
+            if let Some(value) = self.literal() {
+                self.chunk.add_data_value(name_addr, value);
+            }
+            return;
+        }
+
+        self.consume(
+            TokenKind::LeftBracket,
+            "Missing '[': Data arrays must be enclosed by brackets",
+        );
+
+        let mut values: Vec<Value> = vec![];
+
+        loop {
+            let value = match self.literal() {
+                Some(value) => value,
+                None => return,
+            };
+
+            if let Some(first) = values.first() {
+                if first.type_info() != value.type_info() {
+                    return self.error(&format!(
+                        "Elements of data array '{}' must have the same type, found {} and {}",
+                        name.lexeme,
+                        first.type_info(),
+                        value.type_info()
+                    ));
+                }
+            }
+
+            values.push(value);
+
+            if !self.match_advance(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(
+            TokenKind::RightBracket,
+            "Missing ']': Data arrays must be enclosed by brackets",
+        );
+
+        self.chunk.add_data(name_addr, values);
+    }
+
+    /// Parses a literal value, which may be a negative number, without emitting any instruction.
+    /// Strings are interned in the chunk.
+    fn literal(&mut self) -> Option<Value> {
+        let negative = self.match_advance(TokenKind::Minus);
+        self.advance();
+
+        let value = match self.previous.kind {
+            TokenKind::Number => self.number(negative)?,
+            TokenKind::Char if !negative => self.char(),
+            TokenKind::True if !negative => Value::Bool(true),
+            TokenKind::False if !negative => Value::Bool(false),
+            TokenKind::String if !negative => {
+                match self.chunk.add_string(&unescape(self.previous.lexeme)) {
+                    Ok(id) => Value::Str(id),
+                    Err(msg) => {
+                        self.error(msg);
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                self.error("Invalid literal, expected number, character, boolean or string");
+                return None;
+            }
+        };
+
+        Some(value)
+    }
+
     fn label_or_assignment(&mut self) {
         let identifier = self.previous;
 
         if self.match_advance(TokenKind::Colon) {
-            if self.labels.get(identifier.lexeme).is_some() {
+            if self.labels.contains_key(identifier.lexeme) {
                 self.error_at(identifier, "Redefinition of labels is not allowed");
             } else {
-                self.labels.insert(identifier.lexeme, self.chunk.code.len());
+                self.labels
+                    .insert(identifier.lexeme, (self.chunk.code.len(), identifier.line));
             }
+        } else if self.current.kind.is_comparison() {
+            self.discarded_comparison();
         } else {
             self.assignment();
         }
     }
 
+    fn discarded_comparison(&mut self) {
+        self.error_at_current(
+            "The result of this comparison is discarded, assign it to a variable \
+            (x = a == b) or use it in an if statement (if a == b goto label)",
+        );
+    }
+
     fn assignment(&mut self) {
         let dereference = self.previous.kind == TokenKind::Star;
 
@@ -130,17 +303,20 @@ impl<'source, 'c> Compiler<'source, 'c> {
             Ok(addr) => addr,
             Err(_) => return self.error("The program uses too many variables (65535+)"),
         };
+        let subscript = self.check(TokenKind::LeftBracket);
+        let target_pushed = dereference || subscript;
 
         if self.match_advance(TokenKind::LeftBracket) {
             if dereference {
                 return self.error("Dereferenced variables can not be accessed via array indexes in the same statement");
             }
 
-            self.array_subscript();
-            self.consume(
-                TokenKind::RightBracket,
-                "Missing ']': Array accesses must be enclosed by brackets",
-            );
+            // the element address is pushed here, in place of the variable's own address
+            self.array_index();
+            self.emit_instruction(Instruction::IndexAddr(identifier));
+        } else if dereference {
+            // the address held by the variable is the target of the assignment
+            self.get_var(identifier);
         }
 
         self.consume(
@@ -148,12 +324,141 @@ impl<'source, 'c> Compiler<'source, 'c> {
             "Assignment statement expected, but no '=' was found",
         );
 
-        self.emit_instruction(Instruction::GetOrCreateVar(identifier));
-        self.expression();
+        // a = b = expr pushes the address of b and the value of expr, which is duplicated and
+        // assigned to a before being assigned to b
+        let mut targets = vec![identifier];
+
+        loop {
+            if self.check(TokenKind::Call) {
+                if target_pushed {
+                    return self.error_at_current(
+                        "The result of a call can only be assigned to a plain variable",
+                    );
+                }
+
+                // the call pops its parameters, so the target can only be pushed once it is done
+                self.advance();
+                self.call_statement();
+                self.push_targets(&targets);
+                self.emit_instruction(Instruction::PushResult);
+                break;
+            }
+
+            if !self.check(TokenKind::Identifier) {
+                if !target_pushed {
+                    self.push_targets(&targets);
+                }
+                self.expression();
+                break;
+            }
+
+            self.advance();
+
+            match self.current.kind {
+                TokenKind::Equal if target_pushed => {
+                    return self.error_at_current(
+                        "Chained assignments only support plain variables as targets",
+                    );
+                }
+                TokenKind::Equal => {
+                    match self.chunk.add_name(self.previous.lexeme) {
+                        Ok(addr) => targets.push(addr),
+                        Err(_) => {
+                            return self.error("The program uses too many variables (65535+)")
+                        }
+                    }
+                    self.advance();
+                }
+                _ => {
+                    if !target_pushed {
+                        self.push_targets(&targets);
+                    }
+                    self.previous_operand();
+                    self.binary_operation();
+                    break;
+                }
+            }
+        }
+
+        // a later target that is not a plain variable, as in `a = arr[0] = 7` or `a = *p = 3`
+        if self.check(TokenKind::Equal) {
+            return self
+                .error_at_current("Chained assignments only support plain variables as targets");
+        }
+
+        for target in targets[..targets.len() - 1].iter().rev() {
+            self.emit_instruction(Instruction::Dup);
+            self.get_or_create_var(*target);
+            self.emit_instructions(&[Instruction::Swap, Instruction::Assign]);
+        }
         self.emit_instruction(Instruction::Assign);
+
+        // only now, so that the right-hand side can not read the targets it assigns
+        if !target_pushed {
+            self.assigned.extend(targets);
+        }
     }
 
-    fn array_subscript(&mut self) {}
+    /// Pushes the address of the last target of an assignment. Variables are created on top of
+    /// the stack, which must not hold the value being assigned yet, so the other targets of a
+    /// chained assignment are created here too and their addresses pushed again later.
+    fn push_targets(&mut self, targets: &[u16]) {
+        let (last, others) = targets.split_last().unwrap();
+        for target in others {
+            self.get_or_create_var(*target);
+            self.emit_instruction(Instruction::Pop);
+        }
+        self.get_or_create_var(*last);
+    }
+
+    fn get_or_create_var(&mut self, name_addr: u16) {
+        self.emit_instruction(Instruction::GetOrCreateVar(name_addr));
+    }
+
+    /// Emits the read of the variable that was just consumed, which must have been assigned on a
+    /// previous line. Jumps are not followed, so a variable assigned anywhere above counts.
+    fn get_var(&mut self, name_addr: u16) {
+        let defined = self.assigned.contains(&name_addr)
+            || matches!(self.previous.lexeme, "params" | "params_len");
+
+        if !defined {
+            self.unassigned_reads.push((name_addr, self.previous));
+        }
+
+        self.emit_instruction(Instruction::GetVar(name_addr));
+    }
+
+    fn check_unassigned_reads(&mut self) {
+        for (name_addr, token) in std::mem::take(&mut self.unassigned_reads) {
+            if self.chunk.get_data(name_addr).is_none() {
+                self.panic_mode = false;
+                let candidates = self
+                    .assigned
+                    .iter()
+                    .chain(self.chunk.data.iter().map(|array| &array.name))
+                    .map(|name_addr| self.chunk.get_name(*name_addr));
+                let message = did_you_mean(
+                    format!("Variable '{}' is read before being assigned", token.lexeme),
+                    token.lexeme,
+                    candidates,
+                );
+                self.error_at(token, &message);
+            }
+        }
+    }
+
+    /// Compiles the index of an array access, whose '[' was just consumed, up to the closing ']'.
+    fn array_index(&mut self) {
+        if self.check(TokenKind::RightBracket) {
+            return self.error_at_current("Missing index between the brackets of the array access");
+        }
+
+        self.operand();
+        self.consume(
+            TokenKind::RightBracket,
+            "Missing ']': Array accesses must be enclosed by brackets",
+        );
+    }
 
     fn if_statement(&mut self) {
         let negate = self.previous.kind == TokenKind::IfFalse;
@@ -180,7 +485,7 @@ impl<'source, 'c> Compiler<'source, 'c> {
             self.emit_instruction(Instruction::Not);
         }
 
-        let pending = self.pending_labels.entry(label).or_insert_with(Vec::new);
+        let pending = self.pending_labels.entry(label).or_default();
         pending.push((self.chunk.code.len(), self.previous.line));
 
         self.emit_instruction(Instruction::JumpIf(0));
@@ -191,7 +496,7 @@ impl<'source, 'c> Compiler<'source, 'c> {
 
         let label = self.previous.lexeme;
 
-        let pending = self.pending_labels.entry(label).or_insert_with(Vec::new);
+        let pending = self.pending_labels.entry(label).or_default();
         pending.push((self.chunk.code.len(), self.previous.line));
 
         self.emit_instruction(Instruction::Goto(0));
@@ -204,22 +509,82 @@ impl<'source, 'c> Compiler<'source, 'c> {
         self.emit_instruction(Instruction::Print(nl))
     }
 
+    /// `printw value width` prints the value right-aligned in `width` columns.
+    fn print_width_statement(&mut self) {
+        self.operand();
+        self.operand();
+        self.emit_instruction(Instruction::PrintWidth)
+    }
+
+    fn inspect_statement(&mut self) {
+        self.expression();
+        self.emit_instruction(Instruction::Inspect)
+    }
+
+    fn clear_statement(&mut self) {
+        if let Some(addr) = self.array_name("Missing array to be cleared") {
+            self.emit_instruction(Instruction::Clear(addr));
+        }
+    }
+
+    /// Consumes the name of an array operand, returning its name address.
+    fn array_name(&mut self, message: &str) -> Option<u16> {
+        self.consume(TokenKind::Identifier, message);
+
+        match self.chunk.add_name(self.previous.lexeme) {
+            Ok(addr) => Some(addr),
+            Err(_) => {
+                self.error("The program uses too many variables (65535+)");
+                None
+            }
+        }
+    }
+
+    /// `call label count` calls the subroutine at `label` with the last `count` values pushed by
+    /// `param` statements, the count being optional when there are none. See
+    /// `VirtualMachine::call` for how they are made available to the subroutine.
+    ///
+    /// Any label can be called, the subroutine being the code that follows it up to a `return`,
+    /// which resumes the caller after its `call`. Outside of a subroutine, `return` ends the
+    /// program like `halt`. A call used as a value, as in `y = call f`, is followed by a
+    /// `PushResult` reading the value given to `return`.
     fn call_statement(&mut self) {
         self.consume(TokenKind::Identifier, "Missing label to call function");
         let label = self.previous.lexeme;
 
-        if self.match_advance(TokenKind::Comma) {
+        let has_count = self.match_advance(TokenKind::Comma)
+            || !matches!(self.current.kind, TokenKind::NewLine | TokenKind::Eof);
+        if has_count {
             self.operand();
         } else {
             self.make_constant(Value::U64(0));
         }
 
-        let pending = self.pending_labels.entry(label).or_insert_with(Vec::new);
+        let pending = self.pending_labels.entry(label).or_default();
         pending.push((self.chunk.code.len(), self.previous.line));
 
         self.emit_instruction(Instruction::Call(0));
     }
 
+    /// `return` optionally followed by the value handed back to the caller. Returning the result
+    /// of a call is a tail call, the callee returning in place of the current subroutine.
+    fn return_statement(&mut self) {
+        if matches!(self.current.kind, TokenKind::NewLine | TokenKind::Eof) {
+            return self.emit_instruction(Instruction::Return);
+        }
+
+        if self.match_advance(TokenKind::Call) {
+            self.call_statement();
+            if let Some(call @ Instruction::Call(_)) = self.chunk.code.last_mut() {
+                *call = Instruction::TailCall(0);
+            }
+            return;
+        }
+
+        self.expression();
+        self.emit_instruction(Instruction::ReturnValue);
+    }
+
     fn expression(&mut self) {
         if self.unary_expression().is_some() {
             return;
@@ -227,21 +592,41 @@ impl<'source, 'c> Compiler<'source, 'c> {
 
         if self.current.kind == TokenKind::Call {
             self.advance();
-            return self.call_statement();
+            self.call_statement();
+            return self.emit_instruction(Instruction::PushResult);
+        }
+
+        if self.current.kind == TokenKind::Intrinsic {
+            self.advance();
+            return self.intrinsic();
+        }
+
+        if self.current.kind == TokenKind::Checksum {
+            self.advance();
+            if let Some(addr) = self.array_name("Missing array to compute the checksum of") {
+                self.emit_instruction(Instruction::Checksum(addr));
+            }
+            return;
         }
 
         if self.current.kind == TokenKind::Scan {
             self.advance();
-            todo!("Scan expression");
+            return self.scan();
         }
 
         self.operand();
+        self.binary_operation();
+    }
 
+    /// Emits the operator and right operand of a binary expression, if the left operand is
+    /// followed by one.
+    fn binary_operation(&mut self) {
         macro_rules! simple_bin_op {
             ($is:expr) => {{
                 self.advance();
-                self.operand();
+                self.operator_operand();
                 self.emit_instructions($is);
+                self.fold_constants($is);
             }};
         }
 
@@ -255,26 +640,198 @@ impl<'source, 'c> Compiler<'source, 'c> {
             TokenKind::Minus => simple_bin_op!(&[Instruction::Subtract]),
             TokenKind::Plus => simple_bin_op!(&[Instruction::Add]),
             TokenKind::Star => simple_bin_op!(&[Instruction::Multiply]),
+            TokenKind::StarStar => simple_bin_op!(&[Instruction::Power]),
             TokenKind::Slash => simple_bin_op!(&[Instruction::Divide]),
             TokenKind::Percent => simple_bin_op!(&[Instruction::Modulo]),
             TokenKind::ShiftLeft => simple_bin_op!(&[Instruction::ShiftLeft]),
             TokenKind::ShiftRight => simple_bin_op!(&[Instruction::ShiftRight]),
+            TokenKind::Ampersand => simple_bin_op!(&[Instruction::BitAnd]),
+            TokenKind::Pipe => simple_bin_op!(&[Instruction::BitOr]),
+            TokenKind::Caret => simple_bin_op!(&[Instruction::BitXor]),
+            TokenKind::AmpAmp => {
+                self.advance();
+                self.logical_operation(true);
+            }
+            TokenKind::PipePipe => {
+                self.advance();
+                self.logical_operation(false);
+            }
             _ => {}
         };
     }
 
+    /// Emits the right operand of a `&&` (`and`) or `||` operator along with jumps that skip it
+    /// when the left operand, already on the stack, decides the result. Both operands are
+    /// conditions of jumps, so the VM requires them to be booleans.
+    fn logical_operation(&mut self, and: bool) {
+        let left = self.emit_jump(Instruction::JumpIf(0));
+        let mut left_false = None;
+
+        if and {
+            left_false = Some(self.emit_jump(Instruction::Goto(0)));
+            self.patch_jump_here(left);
+        }
+
+        self.operator_operand();
+        let right = self.emit_jump(Instruction::JumpIf(0));
+
+        if let Some(left_false) = left_false {
+            self.patch_jump_here(left_false);
+        }
+        self.emit_instruction(Instruction::False);
+        let end = self.emit_jump(Instruction::Goto(0));
+
+        self.patch_jump_here(right);
+        if !and {
+            self.patch_jump_here(left);
+        }
+        self.emit_instruction(Instruction::True);
+        self.patch_jump_here(end);
+    }
+
+    /// Replaces the operation just emitted by a single constant when both of its operands are
+    /// constants. Operations that fail, such as a division by zero, are left for the VM to report,
+    /// as are integer overflows, whose result depends on the arithmetic mode of the VM. Operands
+    /// whose values were added to the pool just for the operation are removed from it.
+    fn fold_constants(&mut self, operation: &[Instruction]) {
+        let start = match self.chunk.code.len().checked_sub(operation.len() + 2) {
+            Some(start) => start,
+            None => return,
+        };
+
+        let (a, b) = match self.chunk.code[start..start + 2] {
+            [Instruction::Constant(a), Instruction::Constant(b)] => {
+                (self.chunk.get_constant(a), self.chunk.get_constant(b))
+            }
+            _ => return,
+        };
+
+        if operation[0].overflows(a, b) {
+            let (promoted, _) = operation[0].promote(a, b);
+            return warning(&format!(
+                "[line {}] Constant expression overflows, its result does not fit in {}",
+                self.previous.line,
+                promoted.type_info()
+            ));
+        }
+
+        let mut result = match operation[0].apply_binary(a, b) {
+            Some(result) => result,
+            None => return,
+        };
+
+        if let Some(Instruction::Not) = operation.get(1) {
+            result = result.and_then(|mut value| value.logic_negate().map(|_| value));
+        }
+
+        let value = match result {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+
+        self.chunk.code.truncate(start);
+        // the second operand was added last, if both were added by the operation
+        for offset in [start + 1, start] {
+            if self.new_constants.contains(&Some(offset)) {
+                self.chunk.pop_constant();
+            }
+        }
+        self.new_constants = [None; 2];
+        self.make_constant(value);
+    }
+
+    /// `scan` optionally followed by the type of the value to read, `i64` by default.
+    fn scan(&mut self) {
+        let tag = match self.type_keyword() {
+            Some(tag) => tag,
+            None => return self.emit_instruction(Instruction::Scan(TypeTag::I64)),
+        };
+
+        self.advance();
+        self.emit_instruction(Instruction::Scan(tag));
+    }
+
+    /// `(type) operand` converts the operand to the type, see `Value::cast`.
+    fn cast(&mut self) {
+        let tag = match self.type_keyword() {
+            Some(tag) => tag,
+            None => return self.error_at_current("Expected a type to cast to after '('"),
+        };
+
+        self.advance();
+        self.consume(
+            TokenKind::RightParen,
+            "Missing ')' after the type of the cast",
+        );
+        self.operator_operand();
+        self.emit_instruction(Instruction::Cast(tag));
+    }
+
+    /// Type named by the current token, if it is a type keyword.
+    fn type_keyword(&self) -> Option<TypeTag> {
+        match self.current.kind {
+            TokenKind::U64KW => Some(TypeTag::U64),
+            TokenKind::I64KW => Some(TypeTag::I64),
+            TokenKind::F64KW => Some(TypeTag::F64),
+            TokenKind::BoolKW => Some(TypeTag::Bool),
+            TokenKind::CharKW => Some(TypeTag::Char),
+            _ => None,
+        }
+    }
+
+    /// An intrinsic called for its checks alone, such as `clamp x 0 10` failing when the bounds
+    /// are invalid. Its result is discarded.
+    fn discarded_intrinsic(&mut self) {
+        self.intrinsic();
+        self.emit_instruction(Instruction::Pop);
+    }
+
+    fn intrinsic(&mut self) {
+        let intrinsic = match Intrinsic::from_name(self.previous.lexeme) {
+            Some(intrinsic) => intrinsic,
+            None => panic!("Invalid token in intrinsic()"),
+        };
+
+        for _ in 0..intrinsic.arity() {
+            self.operand();
+        }
+
+        self.emit_instruction(Instruction::Intrinsic(intrinsic));
+    }
+
     fn unary_expression(&mut self) -> Option<()> {
         let unary_op = match self.current.kind {
+            TokenKind::Minus => {
+                self.advance();
+                if self.check(TokenKind::Number) {
+                    // a negative literal is a regular operand
+                    self.negative_number();
+                    self.binary_operation();
+                } else {
+                    self.operator_operand();
+                    self.emit_instruction(Instruction::Negate);
+                }
+                return Some(());
+            }
             TokenKind::Bang => Some(Instruction::Not),
-            TokenKind::Minus => Some(Instruction::Negate),
-            TokenKind::Star => todo!("Some(Instruction::Dereference"),
-            TokenKind::Ampersand => todo!("Some(Instruction::Reference"),
+            TokenKind::Tilde => Some(Instruction::BitNot),
+            TokenKind::Star => Some(Instruction::Dereference),
+            TokenKind::Ampersand => {
+                self.advance();
+                self.reference();
+                return Some(());
+            }
+            TokenKind::LeftParen => {
+                self.advance();
+                self.cast();
+                return Some(());
+            }
             _ => None,
         };
 
         if let Some(instruction) = unary_op {
             self.advance();
-            self.operand();
+            self.operator_operand();
             self.emit_instruction(instruction);
             Some(())
         } else {
@@ -282,29 +839,83 @@ impl<'source, 'c> Compiler<'source, 'c> {
         }
     }
 
+    /// Emits the address of the variable following the '&' that was just consumed.
+    fn reference(&mut self) {
+        if !self.match_advance(TokenKind::Identifier) {
+            return self.error_at_current("Only variables can have their address taken");
+        }
+
+        match self.chunk.add_name(self.previous.lexeme) {
+            Ok(addr) => self.emit_instruction(Instruction::Reference(addr)),
+            Err(_) => self.error("The program uses too many variables (65535+)"),
+        }
+    }
+
     fn operand(&mut self) {
         self.advance();
+        self.previous_operand();
+    }
+
+    /// Compiles the operand of the operator that was just consumed.
+    fn operator_operand(&mut self) {
+        if matches!(self.current.kind, TokenKind::NewLine | TokenKind::Eof) {
+            return self.error(&format!(
+                "Expected operand after '{}'",
+                self.previous.lexeme
+            ));
+        }
 
+        self.operand();
+    }
+
+    /// Emits the operand that was just consumed.
+    fn previous_operand(&mut self) {
         match self.previous.kind {
             TokenKind::Identifier => {
                 let addr = match self.chunk.add_name(self.previous.lexeme) {
                     Ok(addr) => addr,
                     Err(_) => return self.error("The program uses too many variables (65535+)"),
                 };
-                self.emit_instruction(Instruction::GetVar(addr));
+
+                if self.match_advance(TokenKind::LeftBracket) {
+                    self.array_index();
+                    self.emit_instruction(Instruction::Index(addr));
+                } else {
+                    self.get_var(addr);
+                }
             }
             TokenKind::True => self.emit_instruction(Instruction::True),
             TokenKind::False => self.emit_instruction(Instruction::False),
-            TokenKind::Char => self.char(),
-            TokenKind::Number => self.number(),
+            TokenKind::Char => {
+                let value = self.char();
+                self.make_constant(value);
+            }
+            TokenKind::Number => {
+                if let Some(value) = self.number(false) {
+                    self.make_constant(value);
+                }
+            }
+            TokenKind::Minus if self.current.kind == TokenKind::Number => self.negative_number(),
 
             // errors
-            TokenKind::String => self.error("String literals are only allowed in the data section"),
+            TokenKind::String => self.error(
+                "String literals are only allowed in data declarations, as in '.data msg = \"hi\"'",
+            ),
             _ => self.error("Invalid operand, expected literal value or variable name"),
         }
     }
 
-    fn number(&mut self) {
+    /// Emits the number following the '-' that was just consumed as a single negative constant.
+    fn negative_number(&mut self) {
+        self.advance();
+        if let Some(value) = self.number(true) {
+            self.make_constant(value);
+        }
+    }
+
+    /// Parses the number that was just consumed, as if preceded by a '-' when `negative` is set.
+    /// Parsing the sign along with the digits lets `-9223372036854775808` be a valid i64.
+    fn number(&mut self, negative: bool) -> Option<Value> {
         enum Type {
             U64,
             I64,
@@ -314,14 +925,49 @@ impl<'source, 'c> Compiler<'source, 'c> {
         let token = self.previous;
         let lexeme = token.lexeme;
 
+        if let Some(hex) = lexeme
+            .strip_prefix("0x")
+            .or_else(|| lexeme.strip_prefix("0X"))
+        {
+            return self.radix_number(hex, 16, negative);
+        }
+        if let Some(bin) = lexeme
+            .strip_prefix("0b")
+            .or_else(|| lexeme.strip_prefix("0B"))
+        {
+            return self.radix_number(bin, 2, negative);
+        }
+        if let Some(oct) = lexeme
+            .strip_prefix("0o")
+            .or_else(|| lexeme.strip_prefix("0O"))
+        {
+            return self.radix_number(oct, 8, negative);
+        }
+
         let mut chars = lexeme.char_indices().peekable();
         let number_begin = 0;
         let mut number_end = lexeme.len();
         let mut nt = Type::I64;
 
-        while let Some((i, c)) = chars.peek() {
+        while let Some(&(i, c)) = chars.peek() {
+            if c == 'e' || c == 'E' {
+                let exponent = &lexeme[i + 1..];
+                let digits = exponent.strip_prefix(&['+', '-'][..]).unwrap_or(exponent);
+                let digits_len = digits
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(digits.len());
+
+                if digits_len == 0 {
+                    self.error("Missing digits in the exponent of float number");
+                    return None;
+                }
+
+                number_end = i + 1 + (exponent.len() - digits.len()) + digits_len;
+                nt = Type::F64;
+                break;
+            }
             if c.is_ascii_alphabetic() {
-                number_end = *i;
+                number_end = i;
                 break;
             }
             let c = chars.next().unwrap().1;
@@ -335,10 +981,15 @@ impl<'source, 'c> Compiler<'source, 'c> {
 
         let number = &lexeme[number_begin..number_end];
         let suffix = &lexeme[suffix_begin..suffix_end];
+        let number = match negative {
+            true => format!("-{}", number),
+            false => number.to_string(),
+        };
 
         let type_info = match (suffix, nt) {
             ("u64", Type::F64) => Err("Cannot set u64 suffix to a float number".into()),
             ("i64", Type::F64) => Err("Cannot set i64 suffix to a float number".into()),
+            ("u64", _) if negative => Err(NEGATIVE_U64.into()),
             ("u64", _) => Ok(Type::U64),
             ("i64", _) => Ok(Type::I64),
             ("f64", _) => Ok(Type::F64),
@@ -350,15 +1001,15 @@ impl<'source, 'c> Compiler<'source, 'c> {
             ($type:ty,$value_type:ident) => {{
                 let result = number.parse::<$type>();
                 match result {
-                    Ok(val) => {
-                        let value = Value::$value_type(val);
-                        self.make_constant(value);
+                    Ok(val) => Some(Value::$value_type(val)),
+                    Err(_) => {
+                        self.error(&format!(
+                            "It was not possible to parse number to type {}",
+                            stringify!($type)
+                        ));
+                        None
                     }
-                    Err(_) => self.error(&format!(
-                        "It was not possible to parse number to type {}",
-                        stringify!($type)
-                    )),
-                };
+                }
             }};
         }
 
@@ -370,26 +1021,88 @@ impl<'source, 'c> Compiler<'source, 'c> {
             },
             Err(msg) => {
                 self.error(&msg);
+                None
             }
+        }
+    }
+
+    /// Parses the digits and suffix of a hexadecimal (`0x`), octal (`0o`) or binary (`0b`) number,
+    /// after its prefix.
+    fn radix_number(&mut self, lexeme: &str, radix: u32, negative: bool) -> Option<Value> {
+        let (name, prefix) = match radix {
+            16 => ("hexadecimal", "0x"),
+            8 => ("octal", "0o"),
+            2 => ("binary", "0b"),
+            _ => panic!("Unsupported radix {}", radix),
         };
+
+        if lexeme.ends_with("f64") {
+            self.error(&format!("Cannot set f64 suffix to a {} number", name));
+            return None;
+        }
+
+        let number_end = lexeme
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(lexeme.len());
+        let (number, suffix) = lexeme.split_at(number_end);
+
+        if number.is_empty() {
+            self.error(&format!(
+                "A {} number must have at least one digit after '{}'",
+                name, prefix
+            ));
+            return None;
+        }
+
+        if let Some(digit) = number.chars().find(|c| !c.is_digit(radix)) {
+            self.error(&format!("Invalid digit '{}' in {} number", digit, name));
+            return None;
+        }
+
+        let value = match suffix {
+            "u64" if negative => {
+                self.error(NEGATIVE_U64);
+                return None;
+            }
+            "u64" => u64::from_str_radix(number, radix).map(Value::U64).ok(),
+            "i64" | "" if negative => i64::from_str_radix(&format!("-{}", number), radix)
+                .map(Value::I64)
+                .ok(),
+            "i64" | "" => i64::from_str_radix(number, radix).map(Value::I64).ok(),
+            suffix => {
+                self.error(&format!("Invalid suffix '{}'", suffix));
+                return None;
+            }
+        };
+
+        if value.is_none() {
+            self.error(&format!(
+                "It was not possible to parse number to type {}",
+                if suffix == "u64" { "u64" } else { "i64" }
+            ));
+        }
+
+        value
     }
 
-    fn r#char(&mut self) {
+    fn r#char(&mut self) -> Value {
         assert_eq!(TokenKind::Char, self.previous.kind);
 
-        let token = self.previous;
-        let lexeme = token.lexeme;
-        let character = lexeme.chars().nth(1);
-
-        match character {
-            Some(c) => self.make_constant(Value::Char(c)),
+        match unescape(self.previous.lexeme).chars().next() {
+            Some(c) => Value::Char(c),
             None => panic!("Invalid token of kind Char"),
         }
     }
 
     fn make_constant(&mut self, value: Value) {
+        let constants = self.chunk.constants_len();
         match self.chunk.add_constant(value) {
-            Ok(idx) => self.emit_instruction(Instruction::Constant(idx)),
+            Ok(idx) => {
+                if self.chunk.constants_len() > constants {
+                    self.new_constants = [self.new_constants[1], Some(self.chunk.code.len())];
+                }
+                self.emit_instruction(Instruction::Constant(idx))
+            }
             Err(msg) => self.error(msg),
         }
     }
@@ -399,7 +1112,7 @@ impl<'source, 'c> Compiler<'source, 'c> {
         let mut missing_labels: Vec<(&str, usize)> = vec![];
 
         for (k, v) in &self.pending_labels {
-            if let Some(idx) = self.labels.get(k) {
+            if let Some((idx, _)) = self.labels.get(k) {
                 for (instruction_idx, _) in v {
                     patches.push((*instruction_idx, *idx));
                 }
@@ -408,7 +1121,12 @@ impl<'source, 'c> Compiler<'source, 'c> {
             }
         }
 
+        // HashMap iteration order is random, sort to keep the output stable between runs
+        missing_labels.sort_by_key(|(label, first_use)| (*first_use, *label));
+
         for (label, first_use) in missing_labels {
+            // each missing label is an independent error, report all of them
+            self.panic_mode = false;
             self.error(&format!(
                 "Missing label '{}', first used in line {}",
                 label, first_use
@@ -420,12 +1138,116 @@ impl<'source, 'c> Compiler<'source, 'c> {
         }
     }
 
+    /// Warns about subroutines that can only ever call themselves again.
+    fn check_recursion(&self) {
+        let mut endless: Vec<&str> = self
+            .labels
+            .iter()
+            .filter(|(_, (offset, _))| {
+                let called = self.chunk.code.iter().any(|instruction| {
+                    matches!(instruction, Instruction::Call(target) | Instruction::TailCall(target) if usize::from(*target) == *offset)
+                });
+                called && recurses_endlessly(&self.chunk.code, *offset)
+            })
+            .map(|(label, _)| *label)
+            .collect();
+        endless.sort_unstable();
+
+        for label in endless {
+            warning(&format!(
+                "Subroutine '{}' calls itself on every path before returning, its recursion never ends",
+                label
+            ));
+        }
+    }
+
+    /// Warns about code that follows a goto, halt or return without a label, which no jump can
+    /// reach. The halt added after the last statement is not reported.
+    fn check_unreachable(&self) {
+        let code = &self.chunk.code;
+        // jumps to labels are not patched yet, the others skip parts of a statement
+        let targets: HashSet<usize> = self
+            .labels
+            .values()
+            .map(|(offset, _)| *offset)
+            .chain(
+                code[self.code_start..]
+                    .iter()
+                    .filter_map(|instruction| match instruction {
+                        Instruction::Goto(ip) | Instruction::JumpIf(ip) => Some(usize::from(*ip)),
+                        _ => None,
+                    }),
+            )
+            .collect();
+        let mut reachable = true;
+        // whether the current unreachable run was reported, each run is reported once
+        let mut reported = false;
+
+        for ip in self.code_start..code.len() {
+            if targets.contains(&ip) {
+                reachable = true;
+            }
+
+            let implicit_halt = self.implicit_halt && ip == code.len() - 1;
+            if !reachable && !reported && !implicit_halt {
+                warning(&format!(
+                    "[line {}] Unreachable code, it follows a goto, halt or return and has no label",
+                    self.chunk.get_line(ip)
+                ));
+                reported = true;
+            }
+
+            if reachable
+                && matches!(
+                    code[ip],
+                    Instruction::Goto(_)
+                        | Instruction::Halt
+                        | Instruction::Return
+                        | Instruction::ReturnValue
+                        | Instruction::TailCall(_)
+                )
+            {
+                reachable = false;
+                reported = false;
+            }
+        }
+    }
+
+    /// Warns about labels that no goto, if or call targets.
+    fn check_unused_labels(&self) {
+        let mut unused: Vec<(usize, &str)> = self
+            .labels
+            .iter()
+            .filter(|(label, _)| !self.pending_labels.contains_key(*label))
+            .map(|(label, (_, line))| (*line, *label))
+            .collect();
+        unused.sort_unstable();
+
+        for (line, label) in unused {
+            warning(&format!(
+                "[line {}] Label '{}' is defined but never used",
+                line, label
+            ));
+        }
+    }
+
+    /// Emits a jump whose target is patched later, returning its offset.
+    fn emit_jump(&mut self, instruction: Instruction) -> usize {
+        self.emit_instruction(instruction);
+        self.chunk.code.len() - 1
+    }
+
+    /// Makes the jump at `idx` target the next instruction to be emitted.
+    fn patch_jump_here(&mut self, idx: usize) {
+        self.patch_jump(idx, self.chunk.code.len() as u16);
+    }
+
     fn patch_jump(&mut self, idx: usize, val: u16) {
         match self.chunk.code.get_mut(idx) {
             Some(i) => match i {
                 Instruction::Goto(idx) => *idx = val,
                 Instruction::JumpIf(idx) => *idx = val,
-                Instruction::Call(idx) => *idx = val,
+                Instruction::Call(idx) | Instruction::TailCall(idx) => *idx = val,
                 _ => panic!("Patching jump led to invalid instruction"),
             },
             None => panic!("Patching jump led to invalid index"),
@@ -433,12 +1255,39 @@ impl<'source, 'c> Compiler<'source, 'c> {
     }
 
     fn end(&mut self) {
-        if self.chunk.code.last() != Some(&Instruction::Halt) {
+        self.check_unassigned_reads();
+
+        if !self.had_error && !is_balanced(&self.chunk.code, self.last_statement) {
+            self.error("The last statement compiled to an incomplete instruction sequence");
+        }
+
+        if self.implicit_halt && self.chunk.code.last() != Some(&Instruction::Halt) {
             self.emit_instruction(Instruction::Halt);
         }
 
+        if !self.had_error {
+            self.check_unreachable();
+        }
+
         self.update_pending_labels();
 
+        if self.chunk.keep_labels || cfg!(feature = "debug_print_code") {
+            // labels of earlier sources come before every label of this one
+            let from = self.chunk.labels.len();
+            let labels = self
+                .labels
+                .iter()
+                .map(|(name, (offset, _))| (name.to_string(), *offset));
+            self.chunk.labels.extend(labels);
+            self.chunk.labels[from..]
+                .sort_by(|(a, a_offset), (b, b_offset)| (a_offset, a).cmp(&(b_offset, b)));
+        }
+
+        if !self.had_error {
+            self.check_recursion();
+            self.check_unused_labels();
+        }
+
         #[cfg(feature = "debug_print_code")]
         if self.had_error {
             let disassembler = crate::disassembler::Disassembler::new(self.chunk);
@@ -482,11 +1331,10 @@ impl<'source, 'c> Compiler<'source, 'c> {
         loop {
             self.current = self.scanner.next_token();
 
-            if self.current.kind != TokenKind::Error {
-                break;
+            match self.current.error {
+                Some(error) => self.error_at_current(&error.to_string()),
+                None => break,
             }
-
-            self.error_at_current(self.current.lexeme);
         }
     }
 
@@ -503,8 +1351,163 @@ impl<'source, 'c> Compiler<'source, 'c> {
             return;
         }
 
-        error_at(token, message);
+        error_at(self.source, token, message);
         self.had_error = true;
         self.panic_mode = true;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        chunk::{Chunk, Instruction},
+        compiler::Compiler,
+        value::Value,
+    };
+
+    fn compile(source: &str) -> Chunk {
+        let mut chunk = Chunk::new();
+        Compiler::compile(source, &mut chunk, true).unwrap();
+        chunk
+    }
+
+    fn constant(chunk: &Chunk, ip: usize) -> Value {
+        match chunk.code[ip] {
+            Instruction::Constant(addr) => chunk.get_constant(addr),
+            instruction => panic!("Expected a constant but found {:?}", instruction),
+        }
+    }
+
+    #[test]
+    fn constant_operations_are_folded() {
+        let folded = compile("x = 1 + 2\n");
+        let unfolded = compile("a = 1\nb = 2\nx = a + b\n");
+
+        assert!(folded.code.len() < unfolded.code.len() - 6);
+        assert!(matches!(
+            folded.code[..],
+            [
+                Instruction::GetOrCreateVar(_),
+                Instruction::Constant(_),
+                Instruction::Assign,
+                Instruction::Halt
+            ]
+        ));
+        assert!(matches!(constant(&folded, 1), Value::I64(3)));
+
+        let compared = compile("x = 2.5 >= 2.5\n");
+        assert_eq!(compared.code.len(), 4);
+        assert!(matches!(constant(&compared, 1), Value::Bool(true)));
+    }
+
+    #[test]
+    fn chained_assignments_duplicate_the_value() {
+        let chunk = compile("a = b = 5\n");
+
+        assert_eq!(
+            chunk.code,
+            vec![
+                Instruction::GetOrCreateVar(0),
+                Instruction::Pop,
+                Instruction::GetOrCreateVar(1),
+                Instruction::Constant(0),
+                Instruction::Dup,
+                Instruction::GetOrCreateVar(0),
+                Instruction::Swap,
+                Instruction::Assign,
+                Instruction::Assign,
+                Instruction::Halt,
+            ]
+        );
+    }
+
+    #[test]
+    fn folded_operands_leave_the_constant_pool() {
+        assert_eq!(compile("x = 1 + 2\n").constants_len(), 1);
+        assert_eq!(compile("x = 1 + 1\n").constants_len(), 1);
+
+        let reused = compile("y = 2\nx = 2 + 3\n");
+        assert_eq!(reused.constants_len(), 2);
+        assert!(matches!(constant(&reused, 1), Value::I64(2)));
+        assert!(matches!(constant(&reused, 4), Value::I64(5)));
+    }
+
+    #[test]
+    fn failing_operations_are_not_folded() {
+        let divided = compile("x = 1 / 0\n");
+        assert!(matches!(divided.code[3], Instruction::Divide));
+
+        let mixed = compile("x = 'a' + 1\n");
+        assert!(matches!(mixed.code[3], Instruction::Add));
+
+        let overflowing = compile("x = 9223372036854775807 + 1\n");
+        assert!(matches!(overflowing.code[3], Instruction::Add));
+
+        let mixed_overflowing = compile("x = 9223372036854775807 + 1u64\n");
+        assert!(matches!(mixed_overflowing.code[3], Instruction::Add));
+    }
+
+    #[test]
+    fn negative_numbers_are_single_constants() {
+        let negative = compile("x = -5\n");
+        assert_eq!(negative.code.len(), 4);
+        assert!(matches!(constant(&negative, 1), Value::I64(-5)));
+
+        let min = compile("x = -9223372036854775808\n");
+        assert!(matches!(constant(&min, 1), Value::I64(i64::MIN)));
+
+        let hex = compile("x = -0x10\n");
+        assert!(matches!(constant(&hex, 1), Value::I64(-16)));
+
+        let indexed = compile(".data a = [1, 2]\nx = a[-1]\n");
+        assert!(matches!(constant(&indexed, 1), Value::I64(-1)));
+
+        let subtracted = compile("x = 1 - -2\n");
+        assert!(matches!(constant(&subtracted, 1), Value::I64(3)));
+
+        let variable = compile("y = 1\nx = -y\n");
+        assert!(variable.code.contains(&Instruction::Negate));
+    }
+
+    #[test]
+    fn negative_u64_literals_are_rejected() {
+        assert!(Compiler::compile("x = -5u64\n", &mut Chunk::new(), true).is_err());
+        assert!(Compiler::compile("x = -0x5u64\n", &mut Chunk::new(), true).is_err());
+        assert!(Compiler::compile("x = -9223372036854775809\n", &mut Chunk::new(), true).is_err());
+    }
+
+    #[test]
+    fn labels_are_recorded_in_the_chunk() {
+        let source = "x = 1\nloop:\ntop:\nx = x + 1\nif x < 5 goto loop\nend:\n";
+        assert!(compile(source).labels.is_empty());
+
+        let mut chunk = Chunk::new();
+        chunk.keep_labels = true;
+        Compiler::compile(source, &mut chunk, true).unwrap();
+
+        assert_eq!(
+            chunk.labels,
+            vec![
+                ("loop".to_string(), 3),
+                ("top".to_string(), 3),
+                ("end".to_string(), 12),
+            ]
+        );
+        assert_eq!(chunk.labels_at(3).collect::<Vec<_>>(), vec!["loop", "top"]);
+        assert_eq!(chunk.labels_at(4).count(), 0);
+    }
+
+    #[test]
+    fn variables_must_be_assigned_before_being_read() {
+        let compiles = |source| Compiler::compile(source, &mut Chunk::new(), true).is_ok();
+
+        assert!(!compiles("y = x + 1\nx = 2\n"));
+        assert!(!compiles("p = 1\n*q = p\n"));
+        assert!(!compiles("x = x + 1u64\n"));
+        assert!(!compiles("a = b = b\n"));
+        assert!(compiles("x = 1\nx = x + 1\n"));
+        assert!(compiles("goto end\nx = 1\nend:\nprint x\n"));
+        assert!(compiles("p = a\n.data a = [1]\n"));
+        assert!(compiles("x = params_len\n"));
+    }
+}
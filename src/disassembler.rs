@@ -20,6 +20,10 @@ impl<'a> Disassembler<'a> {
     pub fn instruction(&self, idx: usize, instruction: &'a Instruction) {
         eprint!("{:04} ", idx);
 
+        for label in self.chunk.labels_at(idx) {
+            eprint!("{}: ", label);
+        }
+
         let line = self.chunk.get_line(idx);
 
         if idx > 0 && line == self.chunk.get_line(idx - 1) {
@@ -30,17 +34,26 @@ impl<'a> Disassembler<'a> {
 
         match instruction {
             Instruction::Return => eprintln!("RETURN"),
+            Instruction::ReturnValue => eprintln!("RETURN_VALUE"),
+            Instruction::PushResult => eprintln!("PUSH_RESULT"),
             Instruction::Add => eprintln!("ADD"),
             Instruction::Subtract => eprintln!("SUBTRACT"),
             Instruction::Multiply => eprintln!("MULTIPLY"),
             Instruction::Divide => eprintln!("DIVIDE"),
             Instruction::Negate => eprintln!("NEGATE"),
+            Instruction::Dereference => eprintln!("DEREFERENCE"),
             Instruction::Modulo => eprintln!("MODULO"),
+            Instruction::Power => eprintln!("POWER"),
             Instruction::ShiftLeft => eprintln!("SHIFT_LEFT"),
             Instruction::ShiftRight => eprintln!("SHIFT_RIGHT"),
+            Instruction::BitAnd => eprintln!("BIT_AND"),
+            Instruction::BitOr => eprintln!("BIT_OR"),
+            Instruction::BitXor => eprintln!("BIT_XOR"),
             Instruction::Not => eprintln!("NOT"),
+            Instruction::BitNot => eprintln!("BIT_NOT"),
             Instruction::Constant(addr) => self.constant("CONSTANT", *addr),
             Instruction::GetOrCreateVar(addr) => self.name("GET_OR_CREATE_VA", *addr),
+            Instruction::Reference(addr) => self.name("REFERENCE", *addr),
             Instruction::GetVar(addr) => self.name("GET_VAR", *addr),
             Instruction::True => eprintln!("TRUE"),
             Instruction::False => eprintln!("FALSE"),
@@ -48,16 +61,31 @@ impl<'a> Disassembler<'a> {
             Instruction::Greater => eprintln!("GREATER"),
             Instruction::Less => eprintln!("LESS"),
             Instruction::Print(nl) => eprintln!("PRINT nl:{}", nl),
+            Instruction::PrintWidth => eprintln!("PRINT_WIDTH"),
+            Instruction::Inspect => eprintln!("INSPECT"),
+            Instruction::Pop => eprintln!("POP"),
+            Instruction::Dup => eprintln!("DUP"),
+            Instruction::Cast(tag) => eprintln!("CAST {}", tag.name()),
+            Instruction::Swap => eprintln!("SWAP"),
             Instruction::Halt => eprintln!("HALT"),
             Instruction::Goto(ip) => eprintln!("JUMP {:04}", ip),
             Instruction::JumpIf(ip) => eprintln!("JUMP {:04}", ip),
             Instruction::Assign => eprintln!("ASSIGN"),
             Instruction::Call(ip) => eprintln!("CALL {:04}", ip),
+            Instruction::TailCall(ip) => eprintln!("TAIL_CALL {:04}", ip),
+            Instruction::Intrinsic(intrinsic) => eprintln!("INTRINSIC {}", intrinsic.name()),
+            Instruction::Clear(addr) => self.name("CLEAR", *addr),
+            Instruction::Checksum(addr) => self.name("CHECKSUM", *addr),
+            Instruction::Index(addr) => self.name("INDEX", *addr),
+            Instruction::IndexAddr(addr) => self.name("INDEX_ADDR", *addr),
+            Instruction::Scan(tag) => eprintln!("{:16} {}", "SCAN", tag.name()),
         }
     }
 
     fn constant(&self, name: &str, addr: u16) {
-        let value = self.chunk.get_constant(addr);
+        let value = self
+            .chunk
+            .display_value(self.chunk.get_constant(addr), false);
         eprintln!("{:16} {:4} '{}'", name, addr, value);
     }
 
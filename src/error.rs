@@ -8,8 +8,27 @@ pub enum TACError {
 
 pub type TACResult<T> = Result<T, TACError>;
 
-pub fn error_at(token: Token, message: &str) {
-    eprint!("[line {}] Error", token.line);
+/// Width of a tab when columns and carets of errors are rendered.
+pub const TAB_WIDTH: usize = 8;
+
+/// Reports an error at a token of `source`, echoing its line with a caret under the token. The
+/// column reported is the one shown by a terminal, so tabs advance to the next tab stop.
+pub fn error_at(source: &str, token: Token, message: &str) {
+    let snippet = match token.kind {
+        TokenKind::Eof => None,
+        _ if token.column == 0 => None,
+        _ => line_of(source, token.start),
+    };
+
+    match (snippet, token.column) {
+        (_, 0) => eprint!("[line {}] Error", token.line),
+        (Some((line, offset)), _) => eprint!(
+            "[line {}, col {}] Error",
+            token.line,
+            display_column(line, offset, TAB_WIDTH) + 1
+        ),
+        (None, column) => eprint!("[line {}, col {}] Error", token.line, column),
+    }
 
     match token.kind {
         TokenKind::Eof => eprint!(" at end"),
@@ -18,4 +37,148 @@ pub fn error_at(token: Token, message: &str) {
     }
 
     eprintln!(": {}", message);
+
+    if let Some((line, offset)) = snippet {
+        eprintln!("    {}", expand_tabs(line, TAB_WIDTH));
+        eprintln!("    {}", caret_line(line, offset, TAB_WIDTH));
+    }
+}
+
+/// Line of `source` containing the byte at `start`, along with the index of that character in
+/// the line.
+fn line_of(source: &str, start: usize) -> Option<(&str, usize)> {
+    let before = source.get(..start)?;
+    let begin = before.rfind(['\n', '\r']).map_or(0, |idx| idx + 1);
+    let end = source[start..]
+        .find(['\n', '\r'])
+        .map_or(source.len(), |idx| start + idx);
+
+    Some((&source[begin..end], before[begin..].chars().count()))
+}
+
+/// Reports a problem that does not stop the program from being compiled and run.
+pub fn warning(message: &str) {
+    eprintln!("Warning: {}", message);
+}
+
+/// Number of single character insertions, deletions and substitutions that turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Finds the candidate closest to a misspelled `name`, if any is within an edit distance of 2 and
+/// does not need to replace the whole name.
+pub fn closest_name<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let len = name.chars().count();
+
+    candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2 && *distance < len)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Appends a suggestion of the closest candidate to the message about a misspelled `name`.
+pub fn did_you_mean<'a>(
+    message: String,
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> String {
+    match closest_name(name, candidates) {
+        Some(candidate) => format!("{}, did you mean '{}'?", message, candidate),
+        None => message,
+    }
+}
+
+/// Computes the terminal column of the character at index `offset` of `line`, where each tab
+/// advances to the next multiple of `tab_width`. Both `offset` and the result start at 0.
+pub fn display_column(line: &str, offset: usize, tab_width: usize) -> usize {
+    line.chars().take(offset).fold(0, |col, c| match c {
+        '\t' if tab_width > 0 => (col / tab_width + 1) * tab_width,
+        '\t' => col,
+        _ => col + 1,
+    })
+}
+
+/// Expands the tabs of `line` into spaces so that it lines up with carets built by `caret_line`.
+pub fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut expanded = String::with_capacity(line.len());
+
+    for c in line.chars() {
+        match c {
+            '\t' if tab_width > 0 => {
+                let col = expanded.chars().count();
+                expanded.push_str(&" ".repeat(tab_width - col % tab_width));
+            }
+            '\t' => {}
+            c => expanded.push(c),
+        }
+    }
+
+    expanded
+}
+
+/// Builds a line with a `^` under the character at index `offset` of `line`.
+pub fn caret_line(line: &str, offset: usize, tab_width: usize) -> String {
+    format!("{}^", " ".repeat(display_column(line, offset, tab_width)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{caret_line, closest_name, display_column, edit_distance, expand_tabs};
+
+    #[test]
+    fn tabs_advance_to_next_tab_stop() {
+        assert_eq!(display_column("\tx = 1", 1, 4), 4);
+        assert_eq!(display_column("a\tx = 1", 2, 4), 4);
+        assert_eq!(display_column("\tx = 1", 1, 8), 8);
+        assert_eq!(display_column("  \t\tx", 4, 4), 8);
+    }
+
+    #[test]
+    fn caret_lines_up_with_tab_indented_line() {
+        let line = "\tx = y + @";
+        let rendered = expand_tabs(line, 4);
+        let caret = caret_line(line, 9, 4);
+
+        assert_eq!(rendered, "    x = y + @");
+        assert_eq!(caret.find('^'), rendered.find('@'));
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("foo", "foo"), 0);
+        assert_eq!(edit_distance("foo", "foobar"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("cnt", "count"), 2);
+        assert_eq!(edit_distance("", "ab"), 2);
+    }
+
+    #[test]
+    fn closest_name_is_within_two_edits() {
+        let names = ["counter", "total", "x"];
+
+        assert_eq!(
+            closest_name("countr", names.iter().copied()),
+            Some("counter")
+        );
+        assert_eq!(closest_name("tota", names.iter().copied()), Some("total"));
+        assert_eq!(closest_name("y", names.iter().copied()), None);
+        assert_eq!(closest_name("index", names.iter().copied()), None);
+    }
 }
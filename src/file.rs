@@ -1,15 +1,151 @@
 use std::fs;
 
-use crate::vm::VirtualMachine;
+use tac::{
+    chunk::Chunk, compiler::Compiler, error::TACError, format, transpile, vm::VirtualMachine,
+};
 
-pub fn run_file(path: &str) {
-    let source = fs::read_to_string(path).expect("Something went wrong reading the file");
-    let mut vm = VirtualMachine::new();
+pub fn run_file(path: &str, mut vm: VirtualMachine) {
+    let source = match read_script(path) {
+        Ok(source) => source,
+        Err(msg) => return eprintln!("{}", msg),
+    };
 
-    match vm.interpret(&source) {
-        Ok(_) => {}
-        Err(_) => eprintln!(
-            "There were errors in the program execution, please check the console log above"
-        ),
+    let result = vm.interpret(&source);
+    if result.is_err() {
+        eprintln!("There were errors in the program execution, please check the console log above");
+    }
+
+    // nothing ran, so there is nothing to report
+    if result == Err(TACError::CompileError) {
+        return;
+    }
+
+    vm.report_coverage();
+    vm.report_profile();
+    vm.report_time();
+}
+
+/// Compiles the script without running it and prints the size of the resulting chunk.
+pub fn report_size(path: &str) {
+    let source = match read_script(path) {
+        Ok(source) => source,
+        Err(msg) => return eprintln!("{}", msg),
+    };
+
+    let mut chunk = Chunk::new();
+    if Compiler::compile(&source, &mut chunk, true).is_err() {
+        return eprintln!(
+            "There were errors in the program compilation, please check the console log above"
+        );
+    }
+
+    println!("Instructions: {}", chunk.code.len());
+    println!("Constants: {}", chunk.constants_len());
+    println!("Names: {}", chunk.names_len());
+    println!("Data arrays: {}", chunk.data.len());
+    println!("Size: {} bytes", chunk.byte_size());
+}
+
+/// Prints the script in its canonical formatting, see `format::format`.
+pub fn format_file(path: &str) {
+    let formatted = read_script(path).and_then(|source| format::format(&source));
+
+    match formatted {
+        Ok(formatted) => print!("{}", formatted),
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
+
+/// Prints the script translated into C, see `transpile::to_c`.
+pub fn emit_c(path: &str) {
+    let source = match read_script(path) {
+        Ok(source) => source,
+        Err(msg) => return eprintln!("{}", msg),
+    };
+
+    let mut chunk = Chunk::new();
+    if Compiler::compile(&source, &mut chunk, true).is_err() {
+        return eprintln!(
+            "There were errors in the program compilation, please check the console log above"
+        );
+    }
+
+    match transpile::to_c(&chunk) {
+        Ok(c) => print!("{}", c),
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
+
+fn read_script(path: &str) -> Result<String, String> {
+    let path = local_path(path)?;
+
+    Ok(fs::read_to_string(path).expect("Something went wrong reading the file"))
+}
+
+/// Converts the script argument into a local path. Plain paths are kept as they are and
+/// `file://` URIs are stripped of their scheme and host and percent-decoded.
+fn local_path(script: &str) -> Result<String, String> {
+    let (scheme, rest) = match script.split_once("://") {
+        Some(parts) => parts,
+        None => return Ok(script.to_string()),
+    };
+
+    if !scheme.eq_ignore_ascii_case("file") {
+        return Err(format!(
+            "Unsupported scheme '{}' in script '{}', only file:// URIs are supported",
+            scheme, script
+        ));
+    }
+
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(i) if &rest[..i] == "localhost" => &rest[i..],
+        _ => {
+            return Err(format!(
+                "Script URI '{}' does not point to a local file",
+                script
+            ))
+        }
+    };
+
+    percent_decode(path).ok_or_else(|| format!("Invalid percent-encoding in script '{}'", script))
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::local_path;
+
+    #[test]
+    fn file_uris_are_converted_to_paths() {
+        assert_eq!(local_path("dir/prog.tac").unwrap(), "dir/prog.tac");
+        assert_eq!(local_path("file:///tmp/prog.tac").unwrap(), "/tmp/prog.tac");
+        assert_eq!(
+            local_path("file://localhost/tmp/my%20prog.tac").unwrap(),
+            "/tmp/my prog.tac"
+        );
+    }
+
+    #[test]
+    fn unsupported_uris_are_rejected() {
+        assert!(local_path("https://example.com/prog.tac").is_err());
+        assert!(local_path("file://server/prog.tac").is_err());
+        assert!(local_path("file:///tmp/bad%2").is_err());
     }
 }
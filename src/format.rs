@@ -0,0 +1,191 @@
+use crate::{
+    scanner::Scanner,
+    token::{Token, TokenKind},
+};
+
+/// Rewrites a program in its canonical form: one statement per line, labels on their own line,
+/// tokens separated by a single space and at most one blank line between statements. Comments
+/// are kept where they are.
+pub fn format(source: &str) -> Result<String, String> {
+    let mut lines: Vec<Vec<Token>> = vec![vec![]];
+
+    for token in Scanner::new(source).with_comments() {
+        match token.kind {
+            TokenKind::Eof => break,
+            TokenKind::NewLine => lines.push(vec![]),
+            TokenKind::Error => {
+                let error = token.error.expect("Scanner error tokens carry their error");
+                return Err(format!("[line {}] Error: {}", token.line, error));
+            }
+            _ => lines.last_mut().unwrap().push(token),
+        }
+    }
+
+    let mut formatted: Vec<String> = vec![];
+
+    for line in &lines {
+        match line.as_slice() {
+            [] => {
+                if matches!(formatted.last(), Some(last) if !last.is_empty()) {
+                    formatted.push(String::new());
+                }
+            }
+            [label, colon, rest @ ..]
+                if label.kind == TokenKind::Identifier && colon.kind == TokenKind::Colon =>
+            {
+                match rest {
+                    [] => formatted.push(format!("{}:", label.lexeme)),
+                    [comment] if comment.kind == TokenKind::Comment => {
+                        formatted.push(format!("{}: {}", label.lexeme, comment.lexeme))
+                    }
+                    rest => {
+                        formatted.push(format!("{}:", label.lexeme));
+                        formatted.push(format_statement(rest));
+                    }
+                }
+            }
+            statement => formatted.push(format_statement(statement)),
+        }
+    }
+
+    while matches!(formatted.last(), Some(last) if last.is_empty()) {
+        formatted.pop();
+    }
+
+    let mut output = formatted.join("\n");
+    output.push('\n');
+    Ok(output)
+}
+
+fn format_statement(tokens: &[Token]) -> String {
+    let mut statement = String::new();
+    let mut previous: Option<Token> = None;
+    // whether the previous token is an operator applied to the token that follows it
+    let mut previous_unary = false;
+
+    for token in tokens {
+        if let Some(previous) = previous {
+            if needs_space(previous, *token, previous_unary) {
+                statement.push(' ');
+            }
+        }
+
+        previous_unary = is_unary_operator(token.kind)
+            && !matches!(previous, Some(previous) if is_operand(previous));
+        statement.push_str(token.lexeme);
+        previous = Some(*token);
+    }
+
+    statement
+}
+
+fn needs_space(previous: Token, current: Token, previous_unary: bool) -> bool {
+    match (previous.kind, current.kind) {
+        (_, TokenKind::Comma | TokenKind::Colon | TokenKind::RightBracket) => false,
+        (TokenKind::LeftBracket | TokenKind::Dot | TokenKind::LeftParen, _) => false,
+        (_, TokenKind::RightParen) => false,
+        (TokenKind::Identifier, TokenKind::LeftBracket) => false,
+        _ => !previous_unary,
+    }
+}
+
+fn is_unary_operator(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Minus
+            | TokenKind::Bang
+            | TokenKind::Tilde
+            | TokenKind::Ampersand
+            | TokenKind::Star
+    )
+}
+
+fn is_operand(token: Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Identifier
+            | TokenKind::Number
+            | TokenKind::Char
+            | TokenKind::String
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::RightBracket
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::format::format;
+
+    #[test]
+    fn messy_program_is_formatted() {
+        let source = "\
+   x=1+2   # sum
+
+
+start:   if x<5    goto end
+  y =  - x
+.data   a=[1 ,2,-3]
+call   f ,2
+*p= &x
+z=a [ 1 ]
+
+
+
+end: # done
+halt
+";
+        let expected = "\
+x = 1 + 2 # sum
+
+start:
+if x < 5 goto end
+y = -x
+.data a = [1, 2, -3]
+call f, 2
+*p = &x
+z = a[1]
+
+end: # done
+halt
+";
+
+        assert_eq!(format(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn block_comments_are_kept() {
+        assert_eq!(
+            format("/* header\n  lines */\nprint  1 /* why */\n").unwrap(),
+            "/* header\n  lines */\nprint 1 /* why */\n"
+        );
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let source = "a = 1\nloop:\nif a >= 3 goto end\na = a + 1\ngoto loop\nend:\nprintln a\n";
+
+        assert_eq!(format(source).unwrap(), source);
+    }
+
+    #[test]
+    fn casts_are_formatted() {
+        assert_eq!(
+            format("y = ( f64 )x\nz = (i64)  - 2.75\n").unwrap(),
+            "y = (f64) x\nz = (i64) -2.75\n"
+        );
+    }
+
+    #[test]
+    fn bitwise_not_is_formatted_like_other_unary_operators() {
+        assert_eq!(format("w = ~ x\nv = ! b\n").unwrap(), "w = ~x\nv = !b\n");
+    }
+
+    #[test]
+    fn scanner_errors_are_reported() {
+        assert_eq!(
+            format("x = 1\ny = 'ab'\n"),
+            Err("[line 2] Error: Character literal may only contain one character".to_string())
+        );
+    }
+}
@@ -0,0 +1,74 @@
+/// Built-in functions invoked by name in expression position, taking their operands separated
+/// by spaces, e.g. `c = toupper c`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Intrinsic {
+    ToUpper,
+    ToLower,
+    ArgCount,
+    Arg,
+    Pack,
+    UnpackHi,
+    UnpackLo,
+    IsNumeric,
+    IsInt,
+    IsFloat,
+    Clamp,
+    StepsLeft,
+    StackLeft,
+}
+
+impl Intrinsic {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "toupper" => Some(Intrinsic::ToUpper),
+            "tolower" => Some(Intrinsic::ToLower),
+            "argc" => Some(Intrinsic::ArgCount),
+            "arg" => Some(Intrinsic::Arg),
+            "pack" => Some(Intrinsic::Pack),
+            "unpack_hi" => Some(Intrinsic::UnpackHi),
+            "unpack_lo" => Some(Intrinsic::UnpackLo),
+            "is_numeric" => Some(Intrinsic::IsNumeric),
+            "is_int" => Some(Intrinsic::IsInt),
+            "is_float" => Some(Intrinsic::IsFloat),
+            "clamp" => Some(Intrinsic::Clamp),
+            "steps_left" => Some(Intrinsic::StepsLeft),
+            "stack_left" => Some(Intrinsic::StackLeft),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Intrinsic::ToUpper => "toupper",
+            Intrinsic::ToLower => "tolower",
+            Intrinsic::ArgCount => "argc",
+            Intrinsic::Arg => "arg",
+            Intrinsic::Pack => "pack",
+            Intrinsic::UnpackHi => "unpack_hi",
+            Intrinsic::UnpackLo => "unpack_lo",
+            Intrinsic::IsNumeric => "is_numeric",
+            Intrinsic::IsInt => "is_int",
+            Intrinsic::IsFloat => "is_float",
+            Intrinsic::Clamp => "clamp",
+            Intrinsic::StepsLeft => "steps_left",
+            Intrinsic::StackLeft => "stack_left",
+        }
+    }
+
+    /// Number of operands the intrinsic pops from the stack.
+    pub fn arity(&self) -> usize {
+        match self {
+            Intrinsic::ArgCount | Intrinsic::StepsLeft | Intrinsic::StackLeft => 0,
+            Intrinsic::ToUpper
+            | Intrinsic::ToLower
+            | Intrinsic::Arg
+            | Intrinsic::UnpackHi
+            | Intrinsic::UnpackLo
+            | Intrinsic::IsNumeric
+            | Intrinsic::IsInt
+            | Intrinsic::IsFloat => 1,
+            Intrinsic::Pack => 2,
+            Intrinsic::Clamp => 3,
+        }
+    }
+}
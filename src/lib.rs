@@ -0,0 +1,23 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use value::FloatStyle;
+
+pub mod chunk;
+pub mod compiler;
+pub mod disassembler;
+pub mod error;
+pub mod format;
+pub mod intrinsic;
+pub mod optimizer;
+pub mod scanner;
+pub mod token;
+pub mod transpile;
+pub mod typecheck;
+pub mod value;
+pub mod vm;
+
+lazy_static! {
+    pub static ref TRACE_EXECUTION: RwLock<bool> = RwLock::new(false);
+    pub static ref FLOAT_STYLE: RwLock<FloatStyle> = RwLock::new(FloatStyle::C);
+}
@@ -1,24 +1,12 @@
-use std::sync::RwLock;
+use std::{process, sync::atomic::Ordering};
 
 use clap::Clap;
-use lazy_static::lazy_static;
 use opts::Opts;
+use tac::{vm::VirtualMachine, FLOAT_STYLE, TRACE_EXECUTION};
 
-mod chunk;
-mod compiler;
-mod disassembler;
-mod error;
 mod file;
 mod opts;
 mod repl;
-mod scanner;
-mod token;
-mod value;
-mod vm;
-
-lazy_static! {
-    static ref TRACE_EXECUTION: RwLock<bool> = RwLock::new(false);
-}
 
 fn main() {
     let opts: Opts = Opts::parse();
@@ -28,8 +16,47 @@ fn main() {
         *guard = opts.trace_execution;
     }
 
+    {
+        let mut guard = FLOAT_STYLE.write().unwrap();
+        *guard = opts.float_style;
+    }
+
+    let mut vm = VirtualMachine::new()
+        .with_args(opts.args)
+        .with_coverage(opts.coverage)
+        .with_profile(opts.profile)
+        .with_timing(opts.time)
+        .with_typecheck(opts.typecheck)
+        .with_optimize(opts.optimize)
+        .with_arithmetic(opts.arithmetic);
+
+    if let Some(limit) = opts.max_steps {
+        vm = vm.with_step_limit(limit);
+    }
+
     match opts.script {
-        Some(path) => file::run_file(&path),
-        None => repl::repl().unwrap(),
+        Some(path) if opts.size => file::report_size(&path),
+        Some(path) if opts.format => file::format_file(&path),
+        Some(path) if opts.emit_c => file::emit_c(&path),
+        Some(path) => {
+            handle_interrupts(&vm);
+            file::run_file(&path, vm)
+        }
+        None => {
+            handle_interrupts(&vm);
+            repl::repl(vm).unwrap()
+        }
     }
 }
+
+/// Makes Ctrl-C stop the running program instead of the whole process. A second Ctrl-C before
+/// the program notices the first, as when it is blocked waiting for input, exits the process.
+fn handle_interrupts(vm: &VirtualMachine) {
+    let interrupted = vm.interrupt_handle();
+    ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::SeqCst) {
+            process::exit(130);
+        }
+    })
+    .expect("Could not set the Ctrl-C handler");
+}
@@ -0,0 +1,281 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::chunk::{Chunk, Instruction};
+
+/// Peephole rules, each replacing a short sequence of instructions by an equivalent shorter one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    /// Removes two consecutive `Not`s applied to a boolean, as in `ifFalse a != b goto label`.
+    DoubleNegation,
+    /// Removes the assignment of a variable to itself, as in `x = x`. The compiler rejects reads
+    /// of variables not assigned before, so the variable already holds the value loaded.
+    RedundantLoad,
+    /// Makes jumps to a `Goto` jump straight to where the chain of gotos ends.
+    JumpThreading,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 3] = [
+        Rule::DoubleNegation,
+        Rule::RedundantLoad,
+        Rule::JumpThreading,
+    ];
+
+    /// Instructions to remove if the rule matches the code starting at `ip`.
+    fn matches(self, code: &[Instruction], ip: usize) -> Option<Range<usize>> {
+        let window = code.get(ip..)?;
+
+        match (self, window) {
+            (
+                Rule::DoubleNegation,
+                [Instruction::Equal
+                | Instruction::Greater
+                | Instruction::Less
+                | Instruction::True
+                | Instruction::False
+                | Instruction::Not, Instruction::Not, Instruction::Not, ..],
+            ) => Some(ip + 1..ip + 3),
+            (
+                Rule::RedundantLoad,
+                [Instruction::GetOrCreateVar(target), Instruction::GetVar(name), Instruction::Assign, ..],
+            ) if target == name => Some(ip..ip + 3),
+            _ => None,
+        }
+    }
+}
+
+/// Applies every rule to the code until none of them matches.
+pub fn optimize(chunk: &mut Chunk) {
+    optimize_with(chunk, &Rule::ALL)
+}
+
+/// Applies the given rules to the code until none of them matches. Instructions that are the
+/// target of a jump or call are never removed, so every jump still lands where it used to.
+pub fn optimize_with(chunk: &mut Chunk, rules: &[Rule]) {
+    if rules.contains(&Rule::JumpThreading) {
+        thread_jumps(&mut chunk.code);
+    }
+
+    loop {
+        let targets: HashSet<usize> = chunk
+            .code
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Goto(ip)
+                | Instruction::JumpIf(ip)
+                | Instruction::Call(ip)
+                | Instruction::TailCall(ip) => Some(usize::from(*ip)),
+                _ => None,
+            })
+            .collect();
+
+        let mut keep = vec![true; chunk.code.len()];
+        let mut changed = false;
+        let mut ip = 0;
+
+        while ip < chunk.code.len() {
+            let removed = rules
+                .iter()
+                .filter_map(|rule| rule.matches(&chunk.code, ip))
+                .find(|removed| !removed.clone().any(|idx| targets.contains(&idx)));
+
+            match removed {
+                Some(removed) => {
+                    ip = removed.end;
+                    keep[removed].iter_mut().for_each(|kept| *kept = false);
+                    changed = true;
+                }
+                None => ip += 1,
+            }
+        }
+
+        if !changed {
+            return;
+        }
+
+        chunk.retain_code(&keep);
+    }
+}
+
+/// Rewrites every `Goto` and `JumpIf` whose target is a `Goto` to target the end of that chain
+/// of gotos instead. Jumps into a cycle of gotos are left as they are.
+fn thread_jumps(code: &mut [Instruction]) {
+    for ip in 0..code.len() {
+        let destination = match code[ip] {
+            Instruction::Goto(target) | Instruction::JumpIf(target) => destination(code, target),
+            _ => continue,
+        };
+
+        match &mut code[ip] {
+            Instruction::Goto(target) | Instruction::JumpIf(target) => *target = destination,
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn destination(code: &[Instruction], target: u16) -> u16 {
+    let mut visited = HashSet::new();
+    let mut current = target;
+
+    while let Some(Instruction::Goto(next)) = code.get(usize::from(current)) {
+        if !visited.insert(current) {
+            return target;
+        }
+        current = *next;
+    }
+
+    current
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        chunk::{Chunk, Instruction},
+        compiler::Compiler,
+        optimizer::{optimize, optimize_with, Rule},
+        vm::VirtualMachine,
+    };
+
+    fn compile(source: &str) -> Chunk {
+        let mut chunk = Chunk::new();
+        // labels are moved along with the code they point to
+        chunk.keep_labels = true;
+        Compiler::compile(source, &mut chunk, true).unwrap();
+        chunk
+    }
+
+    #[test]
+    fn double_negations_are_removed() {
+        let source = "a = 1\nb = 2\nifFalse a != b goto end\nprint a\nend:\n";
+        let mut chunk = compile(source);
+        let before = chunk.code.len();
+
+        optimize(&mut chunk);
+
+        assert_eq!(chunk.code.len(), before - 2);
+        assert!(!chunk
+            .code
+            .windows(2)
+            .any(|pair| pair == [Instruction::Not, Instruction::Not]));
+        // the jump still lands on the halt at the end
+        assert!(chunk
+            .code
+            .contains(&Instruction::JumpIf(chunk.code.len() as u16 - 1)));
+        assert_eq!(
+            chunk.labels,
+            vec![("end".to_string(), chunk.code.len() - 1)]
+        );
+    }
+
+    #[test]
+    fn rules_can_be_chosen() {
+        let mut chunk = compile("x = 1\nifFalse x != 1 goto end\nend:\n");
+        let before = chunk.code.len();
+
+        optimize_with(&mut chunk, &[Rule::RedundantLoad, Rule::JumpThreading]);
+        assert_eq!(chunk.code.len(), before);
+
+        optimize_with(&mut chunk, &[Rule::DoubleNegation]);
+        assert_eq!(chunk.code.len(), before - 2);
+    }
+
+    #[test]
+    fn self_assignments_are_removed() {
+        let mut chunk = compile("x = 1\ny = 2\nx = x\ny = x\n");
+        let before = chunk.code.len();
+
+        optimize(&mut chunk);
+
+        assert_eq!(chunk.code.len(), before - 3);
+        assert_eq!(
+            chunk.code[3..],
+            [
+                Instruction::GetOrCreateVar(1),
+                Instruction::Constant(1),
+                Instruction::Assign,
+                Instruction::GetOrCreateVar(1),
+                Instruction::GetVar(0),
+                Instruction::Assign,
+                Instruction::Halt,
+            ]
+        );
+    }
+
+    #[test]
+    fn jump_targets_are_not_removed() {
+        let mut chunk = Chunk::new();
+        for (instruction, line) in &[
+            (Instruction::True, 1),
+            (Instruction::Goto(3), 1),
+            (Instruction::Not, 2),
+            (Instruction::Not, 3),
+            (Instruction::Halt, 4),
+        ] {
+            chunk.write(*instruction, *line);
+        }
+        let before = chunk.code.clone();
+
+        optimize(&mut chunk);
+
+        assert_eq!(chunk.code, before);
+    }
+
+    #[test]
+    fn optimized_programs_behave_the_same() {
+        let source = "\
+i = 0
+loop:
+i = i + 1
+ifFalse i >= 5 goto loop
+println i
+";
+        let mut vm = VirtualMachine::new().with_optimize(true);
+        let mut out = vec![];
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn chained_gotos_are_threaded() {
+        let source = "\
+x = 1
+if x > 0 goto first
+goto first
+first:
+goto second
+second:
+goto third
+third:
+println x
+";
+        let mut chunk = compile(source);
+        let before = chunk.code.len();
+        let third = chunk.code.len() - 3;
+
+        optimize(&mut chunk);
+
+        assert_eq!(chunk.code.len(), before);
+        assert_eq!(chunk.code[6], Instruction::JumpIf(third as u16));
+        assert_eq!(chunk.code[7], Instruction::Goto(third as u16));
+        assert_eq!(chunk.code[8], Instruction::Goto(third as u16));
+    }
+
+    #[test]
+    fn cyclic_gotos_are_left_alone() {
+        let source = "\
+x = 1
+if x > 0 goto ping
+ping:
+goto pong
+pong:
+goto ping
+";
+        let mut chunk = compile(source);
+        let before = chunk.code.clone();
+
+        optimize(&mut chunk);
+
+        assert_eq!(chunk.code, before);
+    }
+}
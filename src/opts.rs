@@ -1,13 +1,65 @@
 use clap::{AppSettings, Clap};
 
+use tac::value::{ArithmeticMode, FloatStyle};
+
 /// lox interpreter written in Rust
 #[derive(Clap)]
-#[clap(setting = AppSettings::ColoredHelp)]
+#[clap(setting = AppSettings::ColoredHelp, setting = AppSettings::TrailingVarArg)]
 pub struct Opts {
     /// File path for script to be run
     pub script: Option<String>,
 
+    /// Arguments passed to the script, available through the 'argc' and 'arg' intrinsics
+    #[clap(allow_hyphen_values = true)]
+    pub args: Vec<String>,
+
     /// Print all executed instructions in the order they are run
     #[clap(short, long)]
     pub trace_execution: bool,
+
+    /// Report the instructions that were never executed once the program finishes
+    #[clap(long)]
+    pub coverage: bool,
+
+    /// Report how many times each instruction was executed once the program finishes
+    #[clap(long)]
+    pub profile: bool,
+
+    /// Print the time taken and the number of instructions executed once the program finishes
+    #[clap(long)]
+    pub time: bool,
+
+    /// Apply peephole optimizations to the compiled script before running it
+    #[clap(long)]
+    pub optimize: bool,
+
+    /// Check the types of the script before running it and stop if there are type errors
+    #[clap(long)]
+    pub typecheck: bool,
+
+    /// Stop the script with a runtime error once it executes more than this many instructions
+    #[clap(long)]
+    pub max_steps: Option<u64>,
+
+    /// Print the script in its canonical formatting instead of running it
+    #[clap(long)]
+    pub format: bool,
+
+    /// Print an equivalent C program instead of running the script
+    #[clap(long)]
+    pub emit_c: bool,
+
+    /// Compile the script without running it and print the size of the compiled program
+    #[clap(long)]
+    pub size: bool,
+
+    /// What integer operations give when their result does not fit in their type: 'wrap' around,
+    /// a 'checked' runtime error or 'saturate' at the smallest or largest value
+    #[clap(long, default_value = "wrap", possible_values = &["wrap", "checked", "saturate"])]
+    pub arithmetic: ArithmeticMode,
+
+    /// How infinities, NaN and negative zero are printed: 'c' (inf, nan, -0) or 'js' (Infinity,
+    /// NaN, 0)
+    #[clap(long, default_value = "c", possible_values = &["c", "js"])]
+    pub float_style: FloatStyle,
 }
@@ -1,16 +1,18 @@
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use std::fs;
 use std::io;
 
-use crate::vm::VirtualMachine;
+use tac::vm::VirtualMachine;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub fn repl() -> io::Result<()> {
+pub fn repl(mut vm: VirtualMachine) -> io::Result<()> {
     // `()` can be used when no completer is required
     let mut rl = Editor::<()>::new();
 
-    let mut vm = VirtualMachine::new();
+    let mut session = Session::default();
+
     println!("TAC {}", VERSION);
 
     loop {
@@ -23,8 +25,13 @@ pub fn repl() -> io::Result<()> {
 
                 rl.add_history_entry(line.as_str());
 
-                match vm.interpret(&line) {
-                    Ok(()) => {}
+                if let Some(command) = line.strip_prefix(':') {
+                    session.command(command);
+                    continue;
+                }
+
+                match vm.interpret_incremental(&line) {
+                    Ok(()) => session.statements.push(line),
                     Err(err) => println!("Error: {:?}", err),
                 }
             }
@@ -45,3 +52,65 @@ pub fn repl() -> io::Result<()> {
 
     Ok(())
 }
+
+/// Statements entered during a REPL session that were interpreted successfully.
+#[derive(Default)]
+struct Session {
+    statements: Vec<String>,
+}
+
+impl Session {
+    /// Runs a meta-command, given without its leading `:`.
+    fn command(&self, command: &str) {
+        let mut parts = command.split_whitespace();
+
+        match (parts.next(), parts.next()) {
+            (Some("save"), Some(path)) => match self.save(path) {
+                Ok(()) => println!("Saved {} statement(s) to {}", self.statements.len(), path),
+                Err(err) => println!("Error: could not save session to {}: {}", path, err),
+            },
+            (Some("save"), None) => println!("Usage: :save <path>"),
+            _ => println!("Unknown command ':{}'", command),
+        }
+    }
+
+    fn save(&self, path: &str) -> io::Result<()> {
+        let contents: String = self
+            .statements
+            .iter()
+            .map(|statement| format!("{}\n", statement))
+            .collect();
+
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use tac::vm::VirtualMachine;
+
+    use crate::repl::Session;
+
+    #[test]
+    fn saved_session_is_a_runnable_script() {
+        let mut vm = VirtualMachine::new();
+        let mut session = Session::default();
+
+        for line in &["a = 40", "b = a + 2"] {
+            assert!(vm.interpret_incremental(line).is_ok());
+            session.statements.push(line.to_string());
+        }
+
+        let path = env::temp_dir().join(format!("tac-{}-session.tac", process::id()));
+        let path = path.to_str().unwrap();
+        session.save(path).unwrap();
+
+        let saved = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(saved, "a = 40\nb = a + 2\n");
+        assert!(VirtualMachine::new().interpret(&saved).is_ok());
+    }
+}
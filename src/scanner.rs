@@ -1,4 +1,7 @@
-use crate::token::{Token, TokenKind};
+use crate::{
+    intrinsic::Intrinsic,
+    token::{ScanError, Token, TokenKind},
+};
 
 pub struct SourceChar {
     index: usize,
@@ -11,12 +14,23 @@ impl From<(usize, char)> for SourceChar {
     }
 }
 
+/// Splits TAC source code into tokens, either one at a time through `next_token` or as an
+/// iterator that yields every token up to and including `Eof`. Invalid input produces `Error`
+/// tokens carrying a `ScanError`.
 pub struct Scanner<'source> {
     source: &'source str,
     source_chars: Vec<SourceChar>,
     start: usize,
     current: usize,
     line: usize,
+    /// Index of the first character of the current line.
+    line_start: usize,
+    /// Column of the character at `start`.
+    start_column: usize,
+    /// Whether comments are returned as `Comment` tokens instead of being skipped.
+    comments: bool,
+    /// Whether the iterator already yielded `Eof`.
+    finished: bool,
 }
 
 impl<'source> Scanner<'source> {
@@ -27,22 +41,43 @@ impl<'source> Scanner<'source> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_column: 1,
+            comments: false,
+            finished: false,
         }
     }
 
+    /// Returns comments as `Comment` tokens, used by tools that need to preserve them.
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
     pub fn next_token(&mut self) -> Token<'source> {
-        self.skip_non_tokens();
+        if let Err(token) = self.skip_non_tokens() {
+            return token;
+        }
 
         self.start = self.current;
+        self.start_column = self.column();
 
         if self.is_at_end() {
             return self.make_token(TokenKind::Eof);
         }
 
+        if let Some(token) = self.comment() {
+            return token;
+        }
+
         let c = self.advance();
 
         match c {
             '\n' => self.make_token(TokenKind::NewLine),
+            '\r' => {
+                self.match_advance('\n');
+                self.make_token(TokenKind::NewLine)
+            }
             '(' => self.make_token(TokenKind::LeftParen),
             ')' => self.make_token(TokenKind::RightParen),
             '{' => self.make_token(TokenKind::LeftBrace),
@@ -51,14 +86,21 @@ impl<'source> Scanner<'source> {
             ']' => self.make_token(TokenKind::RightBracket),
             ';' => self.make_token(TokenKind::Semicolon),
             ',' => self.make_token(TokenKind::Comma),
+            '.' if matches!(self.peek(), Some(c) if c.is_ascii_digit()) => self.number(),
             '.' => self.make_token(TokenKind::Dot),
             '-' => self.make_token(TokenKind::Minus),
             '+' => self.make_token(TokenKind::Plus),
             '/' => self.make_token(TokenKind::Slash),
+            '*' if self.match_advance('*') => self.make_token(TokenKind::StarStar),
             '*' => self.make_token(TokenKind::Star),
             '%' => self.make_token(TokenKind::Percent),
             ':' => self.make_token(TokenKind::Colon),
+            '&' if self.match_advance('&') => self.make_token(TokenKind::AmpAmp),
             '&' => self.make_token(TokenKind::Ampersand),
+            '|' if self.match_advance('|') => self.make_token(TokenKind::PipePipe),
+            '|' => self.make_token(TokenKind::Pipe),
+            '^' => self.make_token(TokenKind::Caret),
+            '~' => self.make_token(TokenKind::Tilde),
 
             '!' if self.match_advance('=') => self.make_token(TokenKind::BangEqual),
             '!' => self.make_token(TokenKind::Bang),
@@ -80,16 +122,44 @@ impl<'source> Scanner<'source> {
             c if c.is_ascii_alphabetic() || c == '_' => self.identifier(),
             c if c.is_ascii_digit() => self.number(),
 
-            _ => self.error_token("Unexpected character"),
+            c => self.error_token(ScanError::UnexpectedCharacter(c)),
         }
     }
 
     fn string(&mut self) -> Token<'source> {
-        // TODO: handle escaping
-        while self.match_pred_advance(|c| c != '"') {}
+        let mut invalid_escape = None;
+
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => break,
+                '\\' => {
+                    self.advance();
+                    let line = self.line;
+                    match self.escape() {
+                        Escape::Valid => {}
+                        escape => {
+                            invalid_escape.get_or_insert((line, escape));
+                        }
+                    }
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
 
         if self.is_at_end() {
-            self.error_token("Unterminated string")
+            self.error_token(ScanError::UnterminatedString)
+        } else if let Some((line, escape)) = invalid_escape {
+            self.advance();
+            let error = match escape {
+                Escape::InvalidUnicode => ScanError::InvalidUnicodeEscapeInString,
+                _ => ScanError::UnknownEscapeInString,
+            };
+            Token {
+                line,
+                ..self.error_token(error)
+            }
         } else {
             self.advance();
             self.make_token(TokenKind::String)
@@ -97,29 +167,99 @@ impl<'source> Scanner<'source> {
     }
 
     fn r#char(&mut self) -> Token<'source> {
-        // TODO: handle escaping
         let mut count = 0;
-        while self.match_pred_advance(|c| c != '\'') {
+        let mut escape = Escape::Valid;
+
+        while let Some(c) = self.peek() {
+            match c {
+                '\'' | '\n' | '\r' => break,
+                '\\' => {
+                    self.advance();
+                    if escape == Escape::Valid {
+                        escape = self.escape();
+                    }
+                }
+                _ => {
+                    self.advance();
+                }
+            }
             count += 1;
         }
 
-        if self.is_at_end() {
-            self.error_token("Unterminated character")
+        if !self.match_advance('\'') {
+            self.error_token(ScanError::UnterminatedCharacter)
+        } else if escape == Escape::InvalidUnicode {
+            self.error_token(ScanError::InvalidUnicodeEscapeInCharacter)
+        } else if escape == Escape::Unknown {
+            self.error_token(ScanError::UnknownEscapeInCharacter)
+        } else if count == 0 {
+            self.error_token(ScanError::EmptyCharacter)
+        } else if count > 1 {
+            self.error_token(ScanError::MultipleCharacters)
         } else {
-            self.advance();
+            self.make_token(TokenKind::Char)
+        }
+    }
 
-            if count > 1 {
-                self.error_token("Character literal may only contain one character")
-            } else {
-                self.make_token(TokenKind::Char)
+    /// Consumes an escape sequence after its backslash. A line terminator is not consumed, so that
+    /// character literals can report it as unterminated.
+    fn escape(&mut self) -> Escape {
+        match self.peek() {
+            Some('u') => {
+                self.advance();
+                self.unicode_escape()
+            }
+            Some(e) if !self.at_line_end() => {
+                self.advance();
+                match escaped_char(e) {
+                    Some(_) => Escape::Valid,
+                    None => Escape::Unknown,
+                }
             }
+            _ => Escape::Unknown,
+        }
+    }
+
+    /// Consumes the `{XXXX}` part of a `\u{XXXX}` escape sequence.
+    fn unicode_escape(&mut self) -> Escape {
+        if !self.match_advance('{') {
+            return Escape::InvalidUnicode;
+        }
+
+        let start = self.current;
+        while self.match_pred_advance(|c| c.is_ascii_hexdigit()) {}
+        let digits = self.lexeme_at(start, self.current);
+
+        match self.match_advance('}') && unicode_char(digits).is_some() {
+            true => Escape::Valid,
+            false => Escape::InvalidUnicode,
         }
     }
 
     fn number(&mut self) -> Token<'source> {
+        if self.lexeme() == "0"
+            && self.match_pred_advance(|c| matches!(c, 'x' | 'X' | 'o' | 'O' | 'b' | 'B'))
+        {
+            // hexadecimal, octal or binary digits and suffix
+            while self.match_pred_advance(|c| c.is_ascii_alphanumeric()) {}
+
+            return self.make_token(TokenKind::Number);
+        }
+
         while self.match_pred_advance(|c| c.is_ascii_digit()) {}
 
-        if self.match_advance('.') {
+        // the dot of a float such as .5 was consumed before its digits
+        if !self.lexeme().starts_with('.') && self.match_advance('.') {
+            while self.match_pred_advance(|c| c.is_ascii_digit()) {}
+        }
+
+        // exponent, its sign is only part of the number when digits follow it
+        if self.match_pred_advance(|c| c == 'e' || c == 'E') {
+            if matches!(self.peek(), Some('+' | '-'))
+                && matches!(self.peek_next(), Some(c) if c.is_ascii_digit())
+            {
+                self.advance();
+            }
             while self.match_pred_advance(|c| c.is_ascii_digit()) {}
         }
 
@@ -151,14 +291,18 @@ impl<'source> Scanner<'source> {
             "false" => Some(TokenKind::False),
             "print" => Some(TokenKind::Print),
             "println" => Some(TokenKind::PrintLn),
+            "printw" => Some(TokenKind::PrintW),
             "halt" => Some(TokenKind::Halt),
+            "clear" => Some(TokenKind::Clear),
+            "checksum" => Some(TokenKind::Checksum),
+            "inspect" => Some(TokenKind::Inspect),
             "scan" => Some(TokenKind::Scan),
             "u64" => Some(TokenKind::U64KW),
             "i64" => Some(TokenKind::I64KW),
             "f64" => Some(TokenKind::F64KW),
             "char" => Some(TokenKind::CharKW),
             "bool" => Some(TokenKind::BoolKW),
-            _ => None,
+            lexeme => Intrinsic::from_name(lexeme).map(|_| TokenKind::Intrinsic),
         }
     }
 
@@ -191,17 +335,33 @@ impl<'source> Scanner<'source> {
 
         self.current += 1;
 
-        if c == '\n' {
+        // the '\n' of a "\r\n" pair ends the line
+        if c == '\n' || (c == '\r' && self.peek() != Some('\n')) {
             self.line += 1;
+            self.line_start = self.current;
         }
 
         c
     }
 
+    /// Column of the character at `current`.
+    fn column(&self) -> usize {
+        self.current - self.line_start + 1
+    }
+
     fn peek(&self) -> Option<char> {
         self.source_chars.get(self.current).map(|sc| sc.c)
     }
 
+    /// Whether the next characters are a line terminator, either `\n`, `\r\n` or a lone `\r`.
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek(), Some('\n' | '\r'))
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.source_chars.get(self.current + 1).map(|sc| sc.c)
+    }
+
     fn is_at_end(&self) -> bool {
         self.current == self.source_chars.len()
     }
@@ -215,52 +375,577 @@ impl<'source> Scanner<'source> {
             kind,
             lexeme: self.lexeme(),
             line: self.line,
+            column: self.start_column,
+            start: self.byte_offset(self.start),
+            end: self.byte_offset(self.current),
+            error: None,
         }
     }
 
-    fn error_token(&self, message: &'static str) -> Token<'static> {
+    fn error_token(&self, error: ScanError) -> Token<'source> {
         Token {
-            kind: TokenKind::Error,
-            lexeme: message,
-            line: self.line,
+            error: Some(error),
+            ..self.make_token(TokenKind::Error)
         }
     }
 
-    fn skip_non_tokens(&mut self) {
+    /// Skips whitespace and comments up to the next token or new line, returning an error token
+    /// if a block comment is never closed.
+    fn skip_non_tokens(&mut self) -> Result<(), Token<'source>> {
         while let Some(c) = self.peek() {
-            if c == '\n' {
+            if self.at_line_end() {
                 break;
             }
 
-            if c == '#' {
-                self.advance();
-                while let Some(cc) = self.peek() {
-                    if cc == '\n' {
-                        break;
-                    }
-                    self.advance();
-                }
+            if self.comments && (self.at_line_comment() || self.at_block_comment()) {
+                break;
+            } else if self.at_block_comment() {
+                self.block_comment()?;
+            } else if self.at_line_comment() {
+                self.line_comment();
             } else if c.is_whitespace() {
                 self.advance();
             } else {
                 break;
             }
         }
+
+        Ok(())
+    }
+
+    /// Scans a comment starting at `current` when comments are kept as tokens.
+    fn comment(&mut self) -> Option<Token<'source>> {
+        if !self.comments {
+            return None;
+        }
+
+        if self.at_line_comment() {
+            self.line_comment();
+            Some(self.make_token(TokenKind::Comment))
+        } else if self.at_block_comment() {
+            match self.block_comment() {
+                Ok(()) => Some(self.make_token(TokenKind::Comment)),
+                Err(token) => Some(token),
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Whether a line comment, started by either `#` or `//`, begins at `current`.
+    fn at_line_comment(&self) -> bool {
+        match self.peek() {
+            Some('#') => true,
+            Some('/') => self.peek_next() == Some('/'),
+            _ => false,
+        }
+    }
+
+    fn at_block_comment(&self) -> bool {
+        self.peek() == Some('/') && self.peek_next() == Some('*')
+    }
+
+    fn line_comment(&mut self) {
+        while self.peek().is_some() && !self.at_line_end() {
+            self.advance();
+        }
+    }
+
+    fn block_comment(&mut self) -> Result<(), Token<'source>> {
+        let line = self.line;
+        let column = self.column();
+        let start = self.current;
+
+        self.advance();
+        self.advance();
+
+        while let Some(c) = self.peek() {
+            self.advance();
+            if c == '*' && self.match_advance('/') {
+                return Ok(());
+            }
+        }
+
+        Err(Token {
+            lexeme: self.lexeme_at(start, self.current),
+            line,
+            column,
+            start: self.byte_offset(start),
+            ..self.error_token(ScanError::UnterminatedBlockComment)
+        })
     }
 
     fn lexeme_at(&self, start: usize, end: usize) -> &'source str {
-        let left = self
-            .source_chars
-            .get(start)
-            .map(|sc| sc.index)
-            .unwrap_or(self.source.len());
+        &self.source[self.byte_offset(start)..self.byte_offset(end)]
+    }
 
-        let right = self
-            .source_chars
-            .get(end)
+    /// Byte offset in the source of the character at index `index`.
+    fn byte_offset(&self, index: usize) -> usize {
+        self.source_chars
+            .get(index)
             .map(|sc| sc.index)
-            .unwrap_or(self.source.len());
+            .unwrap_or(self.source.len())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Escape {
+    Valid,
+    Unknown,
+    InvalidUnicode,
+}
+
+impl<'source> Iterator for Scanner<'source> {
+    type Item = Token<'source>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = token.kind == TokenKind::Eof;
+
+        Some(token)
+    }
+}
+
+/// Character whose code point is written in hexadecimal by `digits`, the body of a `\u{...}`
+/// escape sequence.
+pub fn unicode_char(digits: &str) -> Option<char> {
+    if digits.is_empty() || digits.len() > 6 {
+        return None;
+    }
+
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// Character represented by the escape sequence made of a backslash followed by `c`.
+pub fn escaped_char(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+/// Decodes the escape sequences of a string or character literal lexeme, quotes included, into
+/// an owned string. The scanner only produces tokens whose escape sequences are valid.
+pub fn unescape(lexeme: &str) -> String {
+    let body = &lexeme[1..lexeme.len() - 1];
+    let mut decoded = String::with_capacity(body.len());
+    let mut chars = body.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('u') => {
+                    let digits: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                    decoded.extend(unicode_char(&digits));
+                }
+                Some(e) => decoded.push(escaped_char(e).unwrap_or(e)),
+                None => decoded.push(c),
+            },
+            c => decoded.push(c),
+        }
+    }
+
+    decoded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        scanner::{unescape, Scanner},
+        token::{ScanError, TokenKind},
+    };
+
+    #[test]
+    fn tokens_carry_their_column() {
+        let mut scanner = Scanner::new("x = x + 1\n  \"ab\" y");
+
+        let columns: Vec<(TokenKind, usize, usize)> = (0..8)
+            .map(|_| scanner.next_token())
+            .map(|token| (token.kind, token.line, token.column))
+            .collect();
+
+        assert_eq!(
+            columns,
+            vec![
+                (TokenKind::Identifier, 1, 1),
+                (TokenKind::Equal, 1, 3),
+                (TokenKind::Identifier, 1, 5),
+                (TokenKind::Plus, 1, 7),
+                (TokenKind::Number, 1, 9),
+                (TokenKind::NewLine, 2, 10),
+                (TokenKind::String, 2, 3),
+                (TokenKind::Identifier, 2, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn crlf_is_a_single_line_terminator() {
+        let mut scanner = Scanner::new("a = 1\r\nb = 2 # two\r\n'x'\r\n");
+
+        let mut lines = vec![];
+        loop {
+            let token = scanner.next_token();
+            match token.kind {
+                TokenKind::Eof => break,
+                TokenKind::NewLine => assert!(token.lexeme == "\r\n"),
+                TokenKind::Error => panic!("Unexpected error token: {:?}", token.error),
+                _ => lines.push((token.lexeme, token.line)),
+            }
+        }
+
+        assert_eq!(
+            lines,
+            vec![
+                ("a", 1),
+                ("=", 1),
+                ("1", 1),
+                ("b", 2),
+                ("=", 2),
+                ("2", 2),
+                ("'x'", 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_cr_is_a_line_terminator() {
+        let mut scanner = Scanner::new("a = 1 # one\rb = 2 // two\r\"x\ry\"\rc");
+
+        let mut tokens = vec![];
+        loop {
+            let token = scanner.next_token();
+            match token.kind {
+                TokenKind::Eof => break,
+                TokenKind::Error => panic!("Unexpected error token: {:?}", token.error),
+                kind => tokens.push((kind, token.line)),
+            }
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Identifier, 1),
+                (TokenKind::Equal, 1),
+                (TokenKind::Number, 1),
+                (TokenKind::NewLine, 2),
+                (TokenKind::Identifier, 2),
+                (TokenKind::Equal, 2),
+                (TokenKind::Number, 2),
+                (TokenKind::NewLine, 3),
+                (TokenKind::String, 4),
+                (TokenKind::NewLine, 5),
+                (TokenKind::Identifier, 5),
+            ]
+        );
+
+        let token = Scanner::new("\"never closed\r").next_token();
+        assert_eq!(token.error, Some(ScanError::UnterminatedString));
+    }
+
+    #[test]
+    fn char_does_not_span_lines() {
+        let token = Scanner::new("'\r\n'").next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::UnterminatedCharacter));
+    }
+
+    #[test]
+    fn leading_dot_floats_are_numbers() {
+        let mut scanner = Scanner::new(".5 .25e1f64 .data .");
+
+        let token = scanner.next_token();
+        assert_eq!((token.kind, token.lexeme), (TokenKind::Number, ".5"));
+        let token = scanner.next_token();
+        assert_eq!((token.kind, token.lexeme), (TokenKind::Number, ".25e1f64"));
+        assert_eq!(scanner.next_token().kind, TokenKind::Dot);
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::Dot);
+        assert_eq!(scanner.next_token().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn doubled_ampersand_and_pipe_are_logical_operators() {
+        let kinds: Vec<TokenKind> = Scanner::new("a && b || c & d | e")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::AmpAmp,
+                TokenKind::Identifier,
+                TokenKind::PipePipe,
+                TokenKind::Identifier,
+                TokenKind::Ampersand,
+                TokenKind::Identifier,
+                TokenKind::Pipe,
+                TokenKind::Identifier,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn double_star_is_power_unless_separated() {
+        let kinds: Vec<TokenKind> = Scanner::new("a ** b * *p")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::StarStar,
+                TokenKind::Identifier,
+                TokenKind::Star,
+                TokenKind::Star,
+                TokenKind::Identifier,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_yields_tokens_up_to_eof() {
+        let kinds: Vec<TokenKind> = Scanner::new("x = 'ab'\n").map(|token| token.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Equal,
+                TokenKind::Error,
+                TokenKind::NewLine,
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_carry_their_byte_span() {
+        let source = "é = 'ü' + §";
+
+        let spans: Vec<(TokenKind, &str)> = Scanner::new(source)
+            .map(|token| (token.kind, &source[token.start..token.end]))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (TokenKind::Error, "é"),
+                (TokenKind::Equal, "="),
+                (TokenKind::Char, "'ü'"),
+                (TokenKind::Plus, "+"),
+                (TokenKind::Error, "§"),
+                (TokenKind::Eof, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn full_line_comment_precedes_statement() {
+        let mut scanner = Scanner::new("# comment\nx = 1 # trailing\n");
+
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Identifier);
+        assert_eq!(token.lexeme, "x");
+        assert_eq!(token.line, 2);
+
+        assert_eq!(scanner.next_token().kind, TokenKind::Equal);
+        assert_eq!(scanner.next_token().kind, TokenKind::Number);
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+        assert_eq!(scanner.next_token().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn shebang_line_is_a_comment() {
+        let mut scanner = Scanner::new("#!/usr/bin/env tac\nx = 1\n");
+
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+
+        let token = scanner.next_token();
+        assert_eq!(
+            (token.kind, token.line, token.column),
+            (TokenKind::Identifier, 2, 1)
+        );
+    }
+
+    #[test]
+    fn block_comments_are_skipped() {
+        let mut scanner = Scanner::new("/* one\ntwo */x = a /*inline*/ / b /**/\n");
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Identifier);
+        assert_eq!(token.line, 2);
+
+        assert_eq!(scanner.next_token().kind, TokenKind::Equal);
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::Slash);
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+        assert_eq!(scanner.next_token().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn unexpected_character_is_named_in_the_error() {
+        let mut scanner = Scanner::new("x = @\n");
+        scanner.next_token();
+        scanner.next_token();
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::UnexpectedCharacter('@')));
+        assert_eq!((token.lexeme, token.line, token.column), ("@", 1, 5));
+        assert_eq!(token.error.unwrap().to_string(), "Unexpected character '@'");
+    }
+
+    #[test]
+    fn double_slash_starts_a_line_comment() {
+        let mut scanner = Scanner::new("a = 1 // hi / there\nb = a / 2\n");
+
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::Equal);
+        assert_eq!(scanner.next_token().kind, TokenKind::Number);
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::Equal);
+        assert_eq!(scanner.next_token().kind, TokenKind::Identifier);
+        assert_eq!(scanner.next_token().kind, TokenKind::Slash);
+        assert_eq!(scanner.next_token().kind, TokenKind::Number);
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
+        assert_eq!(scanner.next_token().kind, TokenKind::Eof);
+
+        let kinds: Vec<TokenKind> = Scanner::new("x // note\n")
+            .with_comments()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Comment,
+                TokenKind::NewLine,
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_an_error() {
+        let mut scanner = Scanner::new("x = 1\n/* never\nclosed *");
+
+        while scanner.next_token().kind != TokenKind::NewLine {}
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::UnterminatedBlockComment));
+        assert_eq!(token.line, 2);
+        assert_eq!((token.start, token.end), (6, 23));
+    }
+
+    #[test]
+    fn strings_with_escapes_are_single_tokens() {
+        let mut scanner = Scanner::new(r#""say \"hi\"\n" "tab\there""#);
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::String);
+        assert_eq!(unescape(token.lexeme), "say \"hi\"\n");
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::String);
+        assert_eq!(unescape(token.lexeme), "tab\there");
+
+        assert_eq!(scanner.next_token().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn escaped_chars_are_single_characters() {
+        for source in [r"'\n'", r"'\t'", r"'\''", r"'\\'", r"'\0'", "'a'"] {
+            let token = Scanner::new(source).next_token();
+            assert_eq!(token.kind, TokenKind::Char, "{}", source);
+            assert_eq!(token.lexeme, source);
+        }
+
+        let token = Scanner::new(r"'\q'").next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::UnknownEscapeInCharacter));
+
+        let token = Scanner::new(r"'ab'").next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::MultipleCharacters));
+    }
+
+    #[test]
+    fn unicode_escapes_are_single_characters() {
+        let token = Scanner::new(r"'\u{1F600}'").next_token();
+        assert_eq!(token.kind, TokenKind::Char);
+        assert_eq!(unescape(token.lexeme), "\u{1F600}");
+
+        let token = Scanner::new(r#""caf\u{e9} \u{41}""#).next_token();
+        assert_eq!(token.kind, TokenKind::String);
+        assert_eq!(unescape(token.lexeme), "café A");
+
+        for source in [r"'\u{D800}'", r"'\u{110000}'", r"'\u{}'", r"'\u41'"] {
+            let token = Scanner::new(source).next_token();
+            assert_eq!(token.kind, TokenKind::Error, "{}", source);
+            assert_eq!(
+                token.error,
+                Some(ScanError::InvalidUnicodeEscapeInCharacter)
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_unicode_escape_in_string_names_its_line() {
+        let mut scanner = Scanner::new("\"ok\n\\u{D800}\"");
+
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::InvalidUnicodeEscapeInString));
+        assert_eq!(token.line, 2);
+    }
+
+    #[test]
+    fn empty_char_is_an_error() {
+        let token = Scanner::new("''").next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::EmptyCharacter));
+    }
+
+    #[test]
+    fn unterminated_escaped_char_is_an_error() {
+        for source in [r"'\", r"'\'", r"'\n"] {
+            let token = Scanner::new(source).next_token();
+            assert_eq!(token.kind, TokenKind::Error, "{}", source);
+            assert_eq!(token.error, Some(ScanError::UnterminatedCharacter));
+        }
+    }
+
+    #[test]
+    fn unknown_escape_is_an_error() {
+        let mut scanner = Scanner::new("\n\"first\nsecond \\q\nthird\"");
+
+        assert_eq!(scanner.next_token().kind, TokenKind::NewLine);
 
-        &self.source[left..right]
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::Error);
+        assert_eq!(token.error, Some(ScanError::UnknownEscapeInString));
+        assert_eq!(token.line, 3);
     }
 }
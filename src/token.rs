@@ -18,6 +18,9 @@ pub enum TokenKind {
     Slash,
     Star,
     Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
     Percent,
     NewLine,
 
@@ -32,6 +35,9 @@ pub enum TokenKind {
     LessEqual,
     ShiftLeft,
     ShiftRight,
+    StarStar,
+    AmpAmp,
+    PipePipe,
 
     // Literals.
     Identifier,
@@ -50,15 +56,21 @@ pub enum TokenKind {
     False,
     Print,
     PrintLn,
+    PrintW,
     Scan,
     Halt,
+    Clear,
+    Checksum,
+    Inspect,
     U64KW,
     I64KW,
     F64KW,
     CharKW,
     BoolKW,
+    Intrinsic,
 
     // Special.
+    Comment,
     Error,
     Eof,
 }
@@ -68,6 +80,57 @@ pub struct Token<'source> {
     pub kind: TokenKind,
     pub lexeme: &'source str,
     pub line: usize,
+    /// Column of the first character of the token in its line, starting at 1. Synthetic tokens
+    /// have column 0.
+    pub column: usize,
+    /// Byte offset in the source of the first character of the token.
+    pub start: usize,
+    /// Byte offset in the source just past the last character of the token.
+    pub end: usize,
+    /// What went wrong, for `Error` tokens produced by the scanner. Their `lexeme` is the
+    /// offending source text.
+    pub error: Option<ScanError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScanError {
+    UnexpectedCharacter(char),
+    UnterminatedString,
+    InvalidUnicodeEscapeInString,
+    UnknownEscapeInString,
+    UnterminatedCharacter,
+    InvalidUnicodeEscapeInCharacter,
+    UnknownEscapeInCharacter,
+    EmptyCharacter,
+    MultipleCharacters,
+    UnterminatedBlockComment,
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanError::UnexpectedCharacter(c) => write!(f, "Unexpected character '{}'", c),
+            ScanError::UnterminatedString => write!(f, "Unterminated string"),
+            ScanError::InvalidUnicodeEscapeInString => {
+                write!(f, "Invalid unicode escape in string literal")
+            }
+            ScanError::UnknownEscapeInString => {
+                write!(f, "Unknown escape sequence in string literal")
+            }
+            ScanError::UnterminatedCharacter => write!(f, "Unterminated character"),
+            ScanError::InvalidUnicodeEscapeInCharacter => {
+                write!(f, "Invalid unicode escape in character literal")
+            }
+            ScanError::UnknownEscapeInCharacter => {
+                write!(f, "Unknown escape sequence in character literal")
+            }
+            ScanError::EmptyCharacter => write!(f, "Character literal can not be empty"),
+            ScanError::MultipleCharacters => {
+                write!(f, "Character literal may only contain one character")
+            }
+            ScanError::UnterminatedBlockComment => write!(f, "Unterminated block comment"),
+        }
+    }
 }
 
 impl<'sourcecode> Token<'sourcecode> {
@@ -76,10 +139,28 @@ impl<'sourcecode> Token<'sourcecode> {
             kind: TokenKind::Error,
             lexeme: text,
             line: 0,
+            column: 0,
+            start: 0,
+            end: 0,
+            error: None,
         }
     }
 }
 
+impl TokenKind {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::BangEqual
+                | TokenKind::EqualEqual
+                | TokenKind::Greater
+                | TokenKind::GreaterEqual
+                | TokenKind::Less
+                | TokenKind::LessEqual
+        )
+    }
+}
+
 impl Display for TokenKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         write!(f, "{:?}", self)
@@ -0,0 +1,570 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{
+    chunk::{Chunk, Instruction},
+    value::Value,
+};
+
+/// Runtime shared by every exported program. Values carry their type like in the VM, so type
+/// errors are still reported at runtime with the same messages.
+const PRELUDE: &str = r#"#include <limits.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+enum tag { UNDEF, F64, U64, I64, BOOL, CHAR, ADDR };
+
+typedef struct {
+    enum tag tag;
+    union {
+        double f;
+        unsigned long long u;
+        long long i;
+        int b;
+        unsigned long c;
+        int a;
+    } as;
+} value;
+
+static value stack[1024];
+static int sp = 0;
+static int line = 0;
+
+static void fail(const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "%s\n[line %d] in script\n", message, line);
+    exit(70);
+}
+
+static void push(value v) {
+    if (sp == (int)(sizeof(stack) / sizeof(stack[0]))) fail("Stack overflow");
+    stack[sp++] = v;
+}
+
+static value pop(void) { return stack[--sp]; }
+
+static value f64(double f) { value v; v.tag = F64; v.as.f = f; return v; }
+static value u64(unsigned long long u) { value v; v.tag = U64; v.as.u = u; return v; }
+static value i64(long long i) { value v; v.tag = I64; v.as.i = i; return v; }
+static value boolean(int b) { value v; v.tag = BOOL; v.as.b = b; return v; }
+static value chr(unsigned long c) { value v; v.tag = CHAR; v.as.c = c; return v; }
+static value addr(int a) { value v; v.tag = ADDR; v.as.a = a; return v; }
+
+static const char *type_name(value v) {
+    static const char *names[] = {"undefined", "f64", "u64", "i64", "bool", "char", "addr"};
+    return names[v.tag];
+}
+
+static void mismatch(const char *op, value a, value b) {
+    char message[128];
+    snprintf(message, sizeof(message),
+             "Operator '%s' not supported between values of type '%s' and '%s'", op,
+             type_name(a), type_name(b));
+    fail(message);
+}
+
+static int is_number(value v) { return v.tag == F64 || v.tag == U64 || v.tag == I64; }
+
+static int is_zero(value v) {
+    return (v.tag == F64 && v.as.f == 0.0) || (v.tag == U64 && v.as.u == 0) ||
+           (v.tag == I64 && v.as.i == 0);
+}
+
+static value to_f64(value v) {
+    return v.tag == U64 ? f64((double)v.as.u) : v.tag == I64 ? f64((double)v.as.i) : v;
+}
+
+static value to_i64(value v) {
+    if (v.tag != U64) return v;
+    if (v.as.u > LLONG_MAX) {
+        char message[128];
+        snprintf(message, sizeof(message),
+                 "Value %llu of type 'u64' does not fit in 'i64' to be mixed with an 'i64'", v.as.u);
+        fail(message);
+    }
+    return i64((long long)v.as.u);
+}
+
+/* Converts numbers of different types to a common type, like Value::promote. */
+static void promote(value *a, value *b) {
+    if (a->tag == b->tag || !is_number(*a) || !is_number(*b)) return;
+    if (a->tag == F64 || b->tag == F64) {
+        *a = to_f64(*a);
+        *b = to_f64(*b);
+    } else {
+        *a = to_i64(*a);
+        *b = to_i64(*b);
+    }
+}
+
+static void arith(const char *op) {
+    value b = pop(), a = pop();
+    promote(&a, &b);
+    if ((op[0] == '/' || op[0] == '%') && is_number(a) && is_zero(b)) fail("Division by 0");
+    if (a.tag != b.tag || !is_number(a)) mismatch(op, a, b);
+
+    unsigned long long x = (unsigned long long)a.as.i, y = (unsigned long long)b.as.i;
+    switch (a.tag) {
+    case F64:
+        switch (op[0]) {
+        case '+': push(f64(a.as.f + b.as.f)); break;
+        case '-': push(f64(a.as.f - b.as.f)); break;
+        case '*': push(f64(a.as.f * b.as.f)); break;
+        case '/': push(f64(a.as.f / b.as.f)); break;
+        default: push(f64(fmod(a.as.f, b.as.f))); break;
+        }
+        break;
+    case U64:
+        switch (op[0]) {
+        case '+': push(u64(a.as.u + b.as.u)); break;
+        case '-': push(u64(a.as.u - b.as.u)); break;
+        case '*': push(u64(a.as.u * b.as.u)); break;
+        case '/': push(u64(a.as.u / b.as.u)); break;
+        default: push(u64(a.as.u % b.as.u)); break;
+        }
+        break;
+    default:
+        switch (op[0]) {
+        case '+': push(i64((long long)(x + y))); break;
+        case '-': push(i64((long long)(x - y))); break;
+        case '*': push(i64((long long)(x * y))); break;
+        case '/': push(i64(b.as.i == -1 ? (long long)(0 - x) : a.as.i / b.as.i)); break;
+        default: push(i64(b.as.i == -1 ? 0 : a.as.i % b.as.i)); break;
+        }
+        break;
+    }
+}
+
+static void bitwise(const char *op) {
+    value b = pop(), a = pop();
+    if (a.tag != b.tag || (a.tag != U64 && a.tag != I64)) mismatch(op, a, b);
+    switch (op[0]) {
+    case '&': a.as.u &= b.as.u; break;
+    case '|': a.as.u |= b.as.u; break;
+    default: a.as.u ^= b.as.u; break;
+    }
+    push(a);
+}
+
+static value shifted(value a, unsigned long long n, int left) {
+    unsigned s = (unsigned)((n > UINT_MAX ? UINT_MAX : n) & 63);
+    if (a.tag == U64) {
+        a.as.u = left ? a.as.u << s : a.as.u >> s;
+    } else {
+        a.as.i = left ? (long long)((unsigned long long)a.as.i << s) : a.as.i >> s;
+    }
+    return a;
+}
+
+static void shift(const char *op) {
+    int left = op[0] == '<';
+    value b = pop(), a = pop();
+    if (!((a.tag == U64 && (b.tag == U64 || b.tag == I64)) || (a.tag == I64 && b.tag == I64))) {
+        mismatch(op, a, b);
+    }
+    if (b.tag == I64 && b.as.i < 0) {
+        push(shifted(a, b.as.i == LLONG_MIN ? LLONG_MAX : -b.as.i, !left));
+    } else {
+        push(shifted(a, b.as.u, left));
+    }
+}
+
+static void compare(const char *op) {
+    value b = pop(), a = pop();
+    promote(&a, &b);
+    if (a.tag != b.tag || a.tag == UNDEF) mismatch(op, a, b);
+
+    int lt, gt, eq;
+    switch (a.tag) {
+    case F64: lt = a.as.f < b.as.f; gt = a.as.f > b.as.f; eq = a.as.f == b.as.f; break;
+    case U64: lt = a.as.u < b.as.u; gt = a.as.u > b.as.u; eq = a.as.u == b.as.u; break;
+    case I64: lt = a.as.i < b.as.i; gt = a.as.i > b.as.i; eq = a.as.i == b.as.i; break;
+    case CHAR: lt = a.as.c < b.as.c; gt = a.as.c > b.as.c; eq = a.as.c == b.as.c; break;
+    case ADDR: lt = a.as.a < b.as.a; gt = a.as.a > b.as.a; eq = a.as.a == b.as.a; break;
+    default: lt = !a.as.b && b.as.b; gt = a.as.b && !b.as.b; eq = a.as.b == b.as.b; break;
+    }
+    push(boolean(op[0] == '<' ? lt : op[0] == '>' ? gt : eq));
+}
+
+static void negate(void) {
+    value v = pop();
+    switch (v.tag) {
+    case F64: push(f64(-v.as.f)); break;
+    case I64: push(i64((long long)(0 - (unsigned long long)v.as.i))); break;
+    case U64: fail("It is not possible to negate a number of type u64"); break;
+    case BOOL: fail("It is not possible to arithmetically negate a boolean"); break;
+    case CHAR: fail("It is not possible to negate a character"); break;
+    default: fail("It is not possible to negate an address"); break;
+    }
+}
+
+static void not(void) {
+    value v = pop();
+    if (v.tag != BOOL) {
+        char message[96];
+        snprintf(message, sizeof(message), "Operator '!' not supported for value of type %s",
+                 type_name(v));
+        fail(message);
+    }
+    push(boolean(!v.as.b));
+}
+
+static void bitnot(void) {
+    value v = pop();
+    switch (v.tag) {
+    case U64: push(u64(~v.as.u)); break;
+    case I64: push(i64(~v.as.i)); break;
+    default: {
+        char message[96];
+        snprintf(message, sizeof(message), "Operator '~' not supported for value of type %s",
+                 type_name(v));
+        fail(message);
+    }
+    }
+}
+
+static int condition(void) {
+    value v = pop();
+    if (v.tag != BOOL) {
+        char message[96];
+        snprintf(message, sizeof(message), "Invalid type '%s' for condition, 'bool' required.",
+                 type_name(v));
+        fail(message);
+    }
+    return v.as.b;
+}
+
+static void print_f64(double f) {
+    if (isnan(f)) { printf("nan"); return; }
+    if (isinf(f)) { printf(f < 0 ? "-inf" : "inf"); return; }
+
+    /* Shortest number of significant digits that reads back as the same value, printed
+       without an exponent. */
+    char digits[64];
+    int precision = 1;
+    for (; precision < 17; precision++) {
+        snprintf(digits, sizeof(digits), "%.*e", precision - 1, f);
+        if (strtod(digits, NULL) == f) break;
+    }
+    snprintf(digits, sizeof(digits), "%.*e", precision - 1, f);
+    int decimals = precision - 1 - atoi(strchr(digits, 'e') + 1);
+    printf("%.*f", decimals > 0 ? decimals : 0, f);
+}
+
+static void print_char(unsigned long c) {
+    if (c < 0x80) {
+        putchar((int)c);
+    } else if (c < 0x800) {
+        putchar((int)(0xC0 | c >> 6));
+        putchar((int)(0x80 | (c & 0x3F)));
+    } else if (c < 0x10000) {
+        putchar((int)(0xE0 | c >> 12));
+        putchar((int)(0x80 | (c >> 6 & 0x3F)));
+        putchar((int)(0x80 | (c & 0x3F)));
+    } else {
+        putchar((int)(0xF0 | c >> 18));
+        putchar((int)(0x80 | (c >> 12 & 0x3F)));
+        putchar((int)(0x80 | (c >> 6 & 0x3F)));
+        putchar((int)(0x80 | (c & 0x3F)));
+    }
+}
+
+static void print_value(value v) {
+    switch (v.tag) {
+    case F64: print_f64(v.as.f); break;
+    case U64: printf("%llu", v.as.u); break;
+    case I64: printf("%lld", v.as.i); break;
+    case BOOL: printf(v.as.b ? "true" : "false"); break;
+    case CHAR: print_char(v.as.c); break;
+    default: printf("addr(%d)", v.as.a); break;
+    }
+}
+
+static void print(int newline) {
+    print_value(pop());
+    if (newline) putchar('\n');
+}
+
+static void inspect(void) {
+    value v = pop();
+    print_value(v);
+    printf(is_number(v) ? "%s\n" : ":%s\n", type_name(v));
+}
+"#;
+
+/// Translates a compiled program into an equivalent C program. Each instruction becomes a call
+/// into a small runtime operating on a stack of tagged values, and jumps become `goto`s. Calls,
+/// intrinsics and data arrays are not supported yet.
+pub fn to_c(chunk: &Chunk) -> Result<String, String> {
+    if !chunk.data.is_empty() {
+        return Err("Data arrays can not be exported to C yet".into());
+    }
+
+    let targets: HashSet<usize> = chunk
+        .code
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Goto(ip) | Instruction::JumpIf(ip) => Some(usize::from(*ip)),
+            _ => None,
+        })
+        .collect();
+
+    let mut c = String::from(PRELUDE);
+
+    let names: Vec<String> = (0..chunk.names_len())
+        .map(|addr| format!("\"{}\"", chunk.get_name(addr as u16)))
+        .collect();
+    writeln!(
+        c,
+        "\nstatic const char *names[] = {{{}}};",
+        names.join(", ")
+    )
+    .unwrap();
+    writeln!(c, "static value vars[{}];", chunk.names_len().max(1)).unwrap();
+    c.push_str(
+        r#"
+static void get_var(int name) {
+    if (vars[name].tag == UNDEF) {
+        char message[128];
+        snprintf(message, sizeof(message), "Variable %s is undefined", names[name]);
+        fail(message);
+    }
+    push(vars[name]);
+}
+
+static void get_or_create_var(int name) {
+    if (vars[name].tag == UNDEF) vars[name] = u64(0);
+    push(addr(name));
+}
+
+static void assign(void) {
+    value v = pop();
+    value target = pop();
+    if (target.tag != ADDR) fail("Assignment target in stack is not valid");
+    vars[target.as.a] = v;
+}
+
+int main(void) {
+"#,
+    );
+
+    let mut line = 0;
+    for (ip, instruction) in chunk.code.iter().enumerate() {
+        if targets.contains(&ip) {
+            writeln!(c, "L{}:", ip).unwrap();
+        }
+
+        let error_line = chunk.get_line(ip);
+        if error_line != line {
+            line = error_line;
+            writeln!(c, "    line = {};", line).unwrap();
+        }
+
+        writeln!(c, "    {}", statement(chunk, instruction)?).unwrap();
+    }
+
+    if targets.contains(&chunk.code.len()) {
+        writeln!(c, "L{}:", chunk.code.len()).unwrap();
+    }
+    c.push_str(
+        "    fail(\"Instruction pointer reached end of code without a finishing statement\");\n",
+    );
+    c.push_str("    return 0;\n}\n");
+
+    Ok(c)
+}
+
+fn statement(chunk: &Chunk, instruction: &Instruction) -> Result<String, String> {
+    let statement = match instruction {
+        Instruction::Halt | Instruction::Return | Instruction::ReturnValue => {
+            "return 0;".to_string()
+        }
+        Instruction::Add => "arith(\"+\");".to_string(),
+        Instruction::Subtract => "arith(\"-\");".to_string(),
+        Instruction::Multiply => "arith(\"*\");".to_string(),
+        Instruction::Divide => "arith(\"/\");".to_string(),
+        Instruction::Modulo => "arith(\"%\");".to_string(),
+        Instruction::ShiftLeft => "shift(\"<<\");".to_string(),
+        Instruction::ShiftRight => "shift(\">>\");".to_string(),
+        Instruction::BitAnd => "bitwise(\"&\");".to_string(),
+        Instruction::BitOr => "bitwise(\"|\");".to_string(),
+        Instruction::BitXor => "bitwise(\"^\");".to_string(),
+        Instruction::Equal => "compare(\"==\");".to_string(),
+        Instruction::Greater => "compare(\">\");".to_string(),
+        Instruction::Less => "compare(\"<\");".to_string(),
+        Instruction::Negate => "negate();".to_string(),
+        Instruction::Not => "not();".to_string(),
+        Instruction::BitNot => "bitnot();".to_string(),
+        Instruction::Pop => "pop();".to_string(),
+        Instruction::Dup => "push(stack[sp - 1]);".to_string(),
+        Instruction::Swap => "{ value b = pop(), a = pop(); push(b); push(a); }".to_string(),
+        Instruction::True => "push(boolean(1));".to_string(),
+        Instruction::False => "push(boolean(0));".to_string(),
+        Instruction::Constant(addr) => format!("push({});", literal(chunk.get_constant(*addr))?),
+        Instruction::GetVar(name) => format!("get_var({});", name),
+        Instruction::GetOrCreateVar(name) => format!("get_or_create_var({});", name),
+        Instruction::Assign => "assign();".to_string(),
+        Instruction::Print(newline) => format!("print({});", *newline as u8),
+        Instruction::Inspect => "inspect();".to_string(),
+        Instruction::Goto(ip) => format!("goto L{};", ip),
+        Instruction::JumpIf(ip) => format!("if (condition()) goto L{};", ip),
+        Instruction::Dereference | Instruction::Reference(_) => {
+            return Err("Pointers can not be exported to C yet".into())
+        }
+        Instruction::Power => return Err("'**' can not be exported to C yet".into()),
+        Instruction::PrintWidth => return Err("printw can not be exported to C yet".into()),
+        Instruction::Scan(_) => return Err("scan can not be exported to C yet".into()),
+        Instruction::Cast(_) => return Err("Casts can not be exported to C yet".into()),
+        Instruction::Call(_) | Instruction::TailCall(_) | Instruction::PushResult => {
+            return Err("Calls can not be exported to C yet".into())
+        }
+        Instruction::Intrinsic(intrinsic) => {
+            return Err(format!(
+                "Intrinsic '{}' can not be exported to C yet",
+                intrinsic.name()
+            ))
+        }
+        Instruction::Clear(_)
+        | Instruction::Checksum(_)
+        | Instruction::Index(_)
+        | Instruction::IndexAddr(_) => {
+            return Err("Data arrays can not be exported to C yet".into())
+        }
+    };
+
+    Ok(statement)
+}
+
+fn literal(value: Value) -> Result<String, String> {
+    let literal = match value {
+        Value::F64(f) if f.is_nan() => "f64(NAN)".to_string(),
+        Value::F64(f) if f.is_infinite() && f > 0.0 => "f64(INFINITY)".to_string(),
+        Value::F64(f) if f.is_infinite() => "f64(-INFINITY)".to_string(),
+        Value::F64(f) => format!("f64({:e})", f),
+        Value::U64(u) => format!("u64({}ULL)", u),
+        Value::I64(i64::MIN) => "i64(LLONG_MIN)".to_string(),
+        Value::I64(i) => format!("i64({}LL)", i),
+        Value::Bool(b) => format!("boolean({})", b as u8),
+        Value::Char(c) => format!("chr({:#x})", c as u32),
+        Value::Addr(a) => format!("addr({})", a),
+        Value::Str(_) => return Err("Strings can not be exported to C yet".into()),
+    };
+
+    Ok(literal)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process::Command};
+
+    use crate::{
+        chunk::Chunk,
+        compiler::Compiler,
+        transpile::{literal, to_c},
+        value::Value,
+        vm::VirtualMachine,
+    };
+
+    fn compile(source: &str) -> Chunk {
+        let mut chunk = Chunk::new();
+        Compiler::compile(source, &mut chunk, true).unwrap();
+        chunk
+    }
+
+    #[test]
+    fn instructions_become_runtime_calls_and_gotos() {
+        let c = to_c(&compile(
+            "i = 0\ntop:\ni = i + 1\nif i < 3 goto top\nprintln i\n",
+        ))
+        .unwrap();
+        let main = &c[c.find("int main(void) {").unwrap()..];
+
+        assert!(main.contains(
+            "L3:\n    line = 3;\n    get_or_create_var(0);\n    get_var(0);\n    push(i64(1LL));\n    arith(\"+\");"
+        ));
+        assert!(main.contains("    line = 4;\n    get_var(0);"));
+        assert!(main.contains("    compare(\"<\");\n    if (condition()) goto L3;"));
+        assert!(main.contains("    print(1);\n    line = 6;\n    return 0;"));
+    }
+
+    #[test]
+    fn unsupported_instructions_are_errors() {
+        assert_eq!(
+            to_c(&compile("x = argc\n")),
+            Err("Intrinsic 'argc' can not be exported to C yet".to_string())
+        );
+        assert_eq!(
+            to_c(&compile(".data a = [1, 2]\nhalt\n")),
+            Err("Data arrays can not be exported to C yet".to_string())
+        );
+        assert_eq!(
+            literal(Value::Str(0)),
+            Err("Strings can not be exported to C yet".to_string())
+        );
+    }
+
+    #[test]
+    fn exported_program_prints_the_same_output() {
+        let source = "\
+i = 0u64
+n = 0.1
+top:
+i = i + 1u64
+n = n + 0.2
+if i < 3u64 goto top
+println i
+println n
+c = 'é'
+println c
+m = i << 60
+println m
+k = m | 5u64
+k = k ^ 1u64
+println k
+x = 7 % 3
+inspect x
+b = x == 1
+println b
+y = x + 0.5
+println y
+z = i - 5
+w = z < i
+println w
+o = b > false
+println o
+";
+        let mut expected = vec![];
+        assert!(VirtualMachine::new()
+            .interpret_to(source, &mut expected)
+            .is_ok());
+
+        let dir = env::temp_dir();
+        let c_path = dir.join(format!("tac-{}-export.c", std::process::id()));
+        let exe_path = dir.join(format!("tac-{}-export", std::process::id()));
+        fs::write(&c_path, to_c(&compile(source)).unwrap()).unwrap();
+
+        // The output can only be compared where a C compiler is installed.
+        let compiled = Command::new("cc")
+            .arg(&c_path)
+            .arg("-o")
+            .arg(&exe_path)
+            .arg("-lm")
+            .status();
+        fs::remove_file(&c_path).unwrap();
+
+        match compiled {
+            Ok(status) if status.success() => {}
+            Ok(status) => panic!("Exported program did not compile: {}", status),
+            Err(_) => return,
+        }
+
+        let output = Command::new(&exe_path).output().unwrap();
+        fs::remove_file(&exe_path).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+}
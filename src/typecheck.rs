@@ -0,0 +1,430 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    chunk::{Chunk, Instruction},
+    value::{TypeTag, Value},
+};
+
+/// What is known about a value in the stack. Types are represented by a sample value of that type,
+/// so that the `Value` operators themselves tell which operations are valid.
+#[derive(Clone, Copy)]
+enum Slot {
+    Known(Value),
+    Unknown,
+    /// Address pushed to assign to a variable, `None` when the variable is not known statically.
+    Target(Option<u16>),
+    /// Address pushed to assign to an element of the data array of a variable.
+    Element(u16),
+}
+
+/// Type of a variable across the whole program.
+#[derive(Clone, Copy)]
+enum VarType {
+    /// Every assignment to the variable has this type.
+    Known(Value),
+    /// The variable is assigned values of different types or written through pointers.
+    Mixed,
+}
+
+/// Looks for type errors in a compiled program without running it, returning the line and
+/// message of each one. Variables are typed by all of their assignments regardless of the order
+/// in which they run, so only variables that always hold values of a single type are checked.
+/// Anything that can not be known statically, such as the values read through pointers, is
+/// assumed to be valid.
+pub fn check(chunk: &Chunk) -> Vec<(usize, String)> {
+    let mut checker = Checker::new(chunk);
+
+    // variables only ever become less precise, so this reaches a fixpoint
+    while checker.walk() {}
+
+    checker.report = true;
+    checker.walk();
+
+    checker.errors
+}
+
+struct Checker<'c> {
+    chunk: &'c Chunk,
+    vars: HashMap<u16, VarType>,
+    /// Type of the elements of each data array, keyed by the variable holding its address.
+    elements: HashMap<u16, VarType>,
+    stack: Vec<Slot>,
+    report: bool,
+    errors: Vec<(usize, String)>,
+}
+
+impl<'c> Checker<'c> {
+    fn new(chunk: &'c Chunk) -> Self {
+        let mut vars = HashMap::new();
+        let mut elements = HashMap::new();
+
+        for array in &chunk.data {
+            if let Some(first) = array.values.first() {
+                if array.scalar {
+                    vars.insert(array.name, VarType::Known(sample(*first)));
+                } else {
+                    vars.insert(array.name, VarType::Known(Value::Addr(0)));
+                    elements.insert(array.name, VarType::Known(sample(*first)));
+                }
+            }
+        }
+
+        // variables whose address is taken can be written through pointers
+        let referenced: HashSet<u16> = chunk
+            .code
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Reference(name) => Some(*name),
+                _ => None,
+            })
+            .collect();
+
+        for name in referenced {
+            vars.insert(name, VarType::Mixed);
+        }
+
+        // arrays whose address is copied can have their elements written through pointers
+        for instruction in &chunk.code {
+            if let Instruction::GetVar(name) | Instruction::Reference(name) = instruction {
+                if let Some(array) = elements.get_mut(name) {
+                    *array = VarType::Mixed;
+                }
+            }
+        }
+
+        Self {
+            chunk,
+            vars,
+            elements,
+            stack: vec![],
+            report: false,
+            errors: vec![],
+        }
+    }
+
+    /// Goes through the whole code once, returning whether the type of any variable changed.
+    fn walk(&mut self) -> bool {
+        let mut changed = false;
+        self.stack.clear();
+
+        for (ip, instruction) in self.chunk.code.iter().enumerate() {
+            changed |= self.instruction(ip, *instruction);
+        }
+
+        changed
+    }
+
+    fn instruction(&mut self, ip: usize, instruction: Instruction) -> bool {
+        match instruction {
+            Instruction::Constant(addr) => self.push(sample(self.chunk.get_constant(addr))),
+            Instruction::True | Instruction::False => self.push(Value::Bool(true)),
+            Instruction::Reference(_) => self.push(Value::Addr(0)),
+            Instruction::Checksum(_) => self.push(Value::U64(1)),
+            Instruction::Scan(tag) => self.push(tag_sample(tag)),
+            Instruction::PushResult => self.stack.push(Slot::Unknown),
+            Instruction::GetVar(name) => self.stack.push(self.var(name)),
+            Instruction::GetOrCreateVar(name) => self.stack.push(Slot::Target(Some(name))),
+            Instruction::Index(name) => {
+                self.index(ip);
+                self.stack.push(known(self.elements.get(&name)));
+            }
+            Instruction::IndexAddr(name) => {
+                self.index(ip);
+                self.stack.push(Slot::Element(name));
+            }
+            Instruction::Dereference => {
+                if let Slot::Known(value) = self.pop() {
+                    if !matches!(value, Value::Addr(_)) {
+                        self.error(
+                            ip,
+                            format!(
+                                "Can not dereference a value of type '{}', 'addr' required",
+                                value.type_info()
+                            ),
+                        );
+                    }
+                }
+                self.stack.push(Slot::Unknown);
+            }
+            Instruction::Negate | Instruction::Not | Instruction::BitNot => {
+                let slot = match self.pop() {
+                    Slot::Known(mut value) => {
+                        let result = match instruction {
+                            Instruction::Negate => value.arithmetic_negate(),
+                            Instruction::BitNot => value.bitwise_negate(),
+                            _ => value.logic_negate(),
+                        };
+                        match result {
+                            Ok(_) => Slot::Known(value),
+                            Err(msg) => {
+                                self.error(ip, msg);
+                                Slot::Unknown
+                            }
+                        }
+                    }
+                    _ => Slot::Unknown,
+                };
+                self.stack.push(slot);
+            }
+            Instruction::JumpIf(_) => {
+                if let Slot::Known(value) = self.pop() {
+                    if !matches!(value, Value::Bool(_)) {
+                        self.error(
+                            ip,
+                            format!(
+                                "Invalid type '{}' for condition, 'bool' required.",
+                                value.type_info()
+                            ),
+                        );
+                    }
+                }
+            }
+            Instruction::Assign => {
+                let value = self.pop();
+                match self.pop() {
+                    Slot::Target(Some(name)) => return record(&mut self.vars, name, value),
+                    Slot::Element(name) => return record(&mut self.elements, name, value),
+                    _ => {}
+                }
+            }
+            Instruction::Intrinsic(intrinsic) => {
+                for _ in 0..intrinsic.arity() {
+                    self.pop();
+                }
+                self.stack.push(Slot::Unknown);
+            }
+            Instruction::Print(_) | Instruction::Inspect | Instruction::Pop => {
+                self.pop();
+            }
+            Instruction::Cast(tag) => {
+                if let Slot::Known(value) = self.pop() {
+                    if let Err(msg) = value.cast(tag) {
+                        self.error(ip, msg);
+                    }
+                }
+                self.push(tag_sample(tag));
+            }
+            Instruction::Dup => {
+                let slot = self.pop();
+                self.stack.push(slot);
+                self.stack.push(slot);
+            }
+            Instruction::Swap => {
+                let b = self.pop();
+                let a = self.pop();
+                self.stack.push(b);
+                self.stack.push(a);
+            }
+            Instruction::PrintWidth => {
+                self.pop();
+                self.pop();
+            }
+            // parameters pushed before a call are consumed by it
+            Instruction::Call(_) | Instruction::TailCall(_) => self.stack.clear(),
+            Instruction::Goto(_)
+            | Instruction::Return
+            | Instruction::ReturnValue
+            | Instruction::Halt => self.stack.clear(),
+            Instruction::Clear(_) => {}
+            _ => {
+                let b = self.pop();
+                let a = self.pop();
+                let slot = match (a, b) {
+                    (Slot::Known(a), Slot::Known(b)) => match instruction.apply_binary(a, b) {
+                        Some(Ok(value)) => Slot::Known(sample(value)),
+                        Some(Err(msg)) => {
+                            self.error(ip, msg);
+                            Slot::Unknown
+                        }
+                        None => Slot::Unknown,
+                    },
+                    _ if matches!(
+                        instruction,
+                        Instruction::Equal | Instruction::Greater | Instruction::Less
+                    ) =>
+                    {
+                        Slot::Known(Value::Bool(true))
+                    }
+                    _ => Slot::Unknown,
+                };
+                self.stack.push(slot);
+            }
+        }
+
+        false
+    }
+
+    fn index(&mut self, ip: usize) {
+        if let Slot::Known(index) = self.pop() {
+            if !index.is_int() {
+                self.error(
+                    ip,
+                    format!(
+                        "Array index must be an integer but found type {}",
+                        index.type_info()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn var(&self, name: u16) -> Slot {
+        known(self.vars.get(&name))
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(Slot::Known(value));
+    }
+
+    fn pop(&mut self) -> Slot {
+        self.stack.pop().unwrap_or(Slot::Unknown)
+    }
+
+    fn error(&mut self, ip: usize, message: String) {
+        if self.report {
+            self.errors.push((self.chunk.get_line(ip), message));
+        }
+    }
+}
+
+/// Records the type of a value assigned to a variable or to the elements of an array, returning
+/// whether it changed.
+fn record(types: &mut HashMap<u16, VarType>, name: u16, value: Slot) -> bool {
+    let assigned = match value {
+        Slot::Known(value) => value,
+        _ => return !matches!(types.insert(name, VarType::Mixed), Some(VarType::Mixed)),
+    };
+
+    match types.get(&name) {
+        None => {
+            types.insert(name, VarType::Known(assigned));
+            true
+        }
+        Some(VarType::Known(current)) if current.type_info() != assigned.type_info() => {
+            types.insert(name, VarType::Mixed);
+            true
+        }
+        Some(_) => false,
+    }
+}
+
+fn known(var: Option<&VarType>) -> Slot {
+    match var {
+        Some(VarType::Known(value)) => Slot::Known(*value),
+        _ => Slot::Unknown,
+    }
+}
+
+/// A value of the same type that no operator rejects for its contents, such as a division by
+/// zero or a negative exponent.
+fn sample(value: Value) -> Value {
+    match value {
+        Value::F64(_) => Value::F64(1.0),
+        Value::U64(_) => Value::U64(1),
+        Value::I64(_) => Value::I64(1),
+        Value::Bool(_) => Value::Bool(true),
+        Value::Char(_) => Value::Char('a'),
+        Value::Addr(_) => Value::Addr(0),
+        Value::Str(_) => Value::Str(0),
+    }
+}
+
+fn tag_sample(tag: TypeTag) -> Value {
+    match tag {
+        TypeTag::U64 => Value::U64(1),
+        TypeTag::I64 => Value::I64(1),
+        TypeTag::F64 => Value::F64(1.0),
+        TypeTag::Bool => Value::Bool(true),
+        TypeTag::Char => Value::Char('a'),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::Chunk, compiler::Compiler, typecheck::check};
+
+    fn errors(source: &str) -> Vec<(usize, String)> {
+        let mut chunk = Chunk::new();
+        Compiler::compile(source, &mut chunk, true).unwrap();
+        check(&chunk)
+    }
+
+    #[test]
+    fn mismatched_operands_are_reported() {
+        let source = "a = true\nb = 1\nc = b + 2\nd = a + b\n";
+
+        assert_eq!(
+            errors(source),
+            vec![(
+                4,
+                "Operator '+' not supported between values of type 'bool' and 'i64'".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn assignments_after_use_are_taken_into_account() {
+        let source = "\
+x = 1
+flag = 2
+goto assign
+use:
+y = x + flag
+halt
+assign:
+flag = true
+goto use
+";
+
+        // flag also holds a bool by the time it is used
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn variables_of_mixed_types_are_not_checked() {
+        let source = "x = 1\nif x > 0 goto float\nx = 1.5\nfloat:\ny = x + 2.5\nb = !x\n";
+
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn conditions_and_operands_are_checked() {
+        let source = "\
+.data a = [1, 2]
+c = 'x'
+if c goto end
+n = -c
+e = a[c]
+end:
+";
+
+        let lines: Vec<usize> = errors(source).iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn variables_written_through_pointers_are_not_checked() {
+        let source = "x = 1\np = &x\n*p = true\ny = x + 1\nz = p + 1\n";
+
+        assert_eq!(
+            errors(source),
+            vec![(
+                5,
+                "Operator '+' not supported between values of type 'addr' and 'i64'".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn data_arrays_hold_the_address_of_their_elements() {
+        let source = ".data a = [1, 2]\nx = a[0] + 1\ny = a + 1\n";
+
+        assert_eq!(
+            errors(source),
+            vec![(
+                3,
+                "Operator '+' not supported between values of type 'addr' and 'i64'".to_string()
+            )]
+        );
+    }
+}
@@ -1,9 +1,95 @@
 use std::{
-    convert::TryInto,
+    cmp::Ordering,
+    convert::{TryFrom, TryInto},
     fmt::{Display, Formatter},
     ops::{self, Shl, Shr},
+    str::FromStr,
 };
 
+/// How floats without a plain decimal representation, infinities, NaN and negative zero, are
+/// printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatStyle {
+    /// `inf`, `-inf`, `nan` and `-0`, like C's printf. This is the default.
+    C,
+    /// `Infinity`, `-Infinity`, `NaN` and `0`, like JavaScript.
+    Js,
+}
+
+impl FloatStyle {
+    pub fn format(self, val: f64) -> String {
+        match self {
+            FloatStyle::C if val.is_nan() => "nan".to_string(),
+            FloatStyle::C if val == f64::INFINITY => "inf".to_string(),
+            FloatStyle::C if val == f64::NEG_INFINITY => "-inf".to_string(),
+            FloatStyle::Js if val.is_nan() => "NaN".to_string(),
+            FloatStyle::Js if val == f64::INFINITY => "Infinity".to_string(),
+            FloatStyle::Js if val == f64::NEG_INFINITY => "-Infinity".to_string(),
+            FloatStyle::Js if val == 0.0 => "0".to_string(),
+            _ => format!("{}", val),
+        }
+    }
+}
+
+impl FromStr for FloatStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c" => Ok(FloatStyle::C),
+            "js" => Ok(FloatStyle::Js),
+            s => Err(format!("Invalid float style '{}'", s)),
+        }
+    }
+}
+
+/// What integer additions, subtractions, multiplications, divisions and powers give when their
+/// result does not fit in the type of their operands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArithmeticMode {
+    /// The result wraps around, keeping its lowest bits. This is the default.
+    Wrap,
+    /// The operation is a runtime error.
+    Checked,
+    /// The result is clamped to the smallest or largest value of the type.
+    Saturate,
+}
+
+impl FromStr for ArithmeticMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(ArithmeticMode::Wrap),
+            "checked" => Ok(ArithmeticMode::Checked),
+            "saturate" => Ok(ArithmeticMode::Saturate),
+            s => Err(format!("Invalid arithmetic mode '{}'", s)),
+        }
+    }
+}
+
+/// Type of a value, as named by the type keywords of the language.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeTag {
+    U64,
+    I64,
+    F64,
+    Bool,
+    Char,
+}
+
+impl TypeTag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TypeTag::U64 => "u64",
+            TypeTag::I64 => "i64",
+            TypeTag::F64 => "f64",
+            TypeTag::Bool => "bool",
+            TypeTag::Char => "char",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Value {
     F64(f64),
@@ -12,6 +98,8 @@ pub enum Value {
     Bool(bool),
     Char(char),
     Addr(usize),
+    /// Id of a string interned in the chunk, which holds its text. See `Chunk::get_string`.
+    Str(u16),
 }
 
 impl Value {
@@ -23,12 +111,14 @@ impl Value {
             }
             Value::U64(_) => Err("It is not possible to negate a number of type u64".into()),
             Value::I64(val) => {
-                *val = -(*val);
+                // like the other integer operations, negating i64::MIN wraps around to itself
+                *val = val.wrapping_neg();
                 Ok(())
             }
             Value::Bool(_) => Err("It is not possible to arithmetically negate a boolean".into()),
             Value::Char(_) => Err("It is not possible to negate a character".into()),
             Value::Addr(_) => Err("It is not possible to negate an address".into()),
+            Value::Str(_) => Err("It is not possible to negate a string".into()),
         }
     }
 
@@ -45,12 +135,263 @@ impl Value {
         }
     }
 
+    /// Flips every bit of an integer, keeping its type.
+    pub fn bitwise_negate(&mut self) -> Result<(), String> {
+        match self {
+            Value::U64(val) => {
+                *val = !(*val);
+                Ok(())
+            }
+            Value::I64(val) => {
+                *val = !(*val);
+                Ok(())
+            }
+            val => Err(format!(
+                "Operator '~' not supported for value of type {}",
+                val.type_info()
+            )),
+        }
+    }
+
+    /// Converts a character to its uppercase form. Characters whose uppercase form is made of
+    /// more than one character, such as 'ß', are left unchanged.
+    pub fn to_uppercase(self) -> Result<Value, String> {
+        match self {
+            Value::Char(c) => {
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(u), None) => Ok(Value::Char(u)),
+                    _ => Ok(Value::Char(c)),
+                }
+            }
+            val => Err(format!(
+                "Intrinsic 'toupper' not supported for value of type {}",
+                val.type_info()
+            )),
+        }
+    }
+
+    /// Converts a character to its lowercase form. Characters whose lowercase form is made of
+    /// more than one character, such as 'İ', are left unchanged.
+    pub fn to_lowercase(self) -> Result<Value, String> {
+        match self {
+            Value::Char(c) => {
+                let mut lower = c.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(l), None) => Ok(Value::Char(l)),
+                    _ => Ok(Value::Char(c)),
+                }
+            }
+            val => Err(format!(
+                "Intrinsic 'tolower' not supported for value of type {}",
+                val.type_info()
+            )),
+        }
+    }
+
+    /// Parses a program argument into the first matching value of type bool, i64, u64, f64 or
+    /// char, in that order.
+    pub fn from_arg(arg: &str) -> Result<Value, String> {
+        if let Ok(val) = arg.parse::<bool>() {
+            return Ok(Value::Bool(val));
+        }
+        if let Ok(val) = arg.parse::<i64>() {
+            return Ok(Value::I64(val));
+        }
+        if let Ok(val) = arg.parse::<u64>() {
+            return Ok(Value::U64(val));
+        }
+        if let Ok(val) = arg.parse::<f64>() {
+            return Ok(Value::F64(val));
+        }
+        if let Ok(val) = arg.parse::<char>() {
+            return Ok(Value::Char(val));
+        }
+
+        Err(format!(
+            "Argument '{}' is not a boolean, number or character",
+            arg
+        ))
+    }
+
+    /// Parses text read by `scan` into a value of the given type. Surrounding whitespace is
+    /// ignored, except for chars, which may be whitespace themselves and only lose the line
+    /// terminator.
+    pub fn parse(text: &str, tag: TypeTag) -> Result<Value, String> {
+        let trimmed = match tag {
+            TypeTag::Char => text.trim_end_matches(&['\n', '\r'][..]),
+            _ => text.trim(),
+        };
+        let value = match tag {
+            TypeTag::U64 => trimmed.parse().ok().map(Value::U64),
+            TypeTag::I64 => trimmed.parse().ok().map(Value::I64),
+            TypeTag::F64 => trimmed.parse().ok().map(Value::F64),
+            TypeTag::Bool => trimmed.parse().ok().map(Value::Bool),
+            TypeTag::Char => trimmed.parse().ok().map(Value::Char),
+        };
+
+        value.ok_or_else(|| {
+            format!(
+                "Could not parse '{}' as a value of type {}",
+                trimmed,
+                tag.name()
+            )
+        })
+    }
+
+    /// Converts the value to the given type. Floats are truncated towards zero when converted to
+    /// integers and integers are rounded to the nearest float, chars convert to and from their
+    /// code point and bools to and from 0 and 1, any other integer being `true`. Values that do
+    /// not fit in the new type are errors, as are conversions between floats and bools or chars.
+    pub fn cast(self, to: TypeTag) -> Result<Value, String> {
+        // smallest floats above the largest integers, which are powers of two and thus exact
+        const I64_LIMIT: f64 = 9_223_372_036_854_775_808.0;
+        const U64_LIMIT: f64 = 18_446_744_073_709_551_616.0;
+
+        let out_of_range = || format!("Value {} does not fit in type {}", self, to.name());
+        let invalid_char = || format!("Value {} is not a valid character code", self);
+
+        let value = match (self, to) {
+            (Value::U64(_), TypeTag::U64)
+            | (Value::I64(_), TypeTag::I64)
+            | (Value::F64(_), TypeTag::F64)
+            | (Value::Bool(_), TypeTag::Bool)
+            | (Value::Char(_), TypeTag::Char) => self,
+            (Value::U64(u), TypeTag::I64) => {
+                Value::I64(i64::try_from(u).map_err(|_| out_of_range())?)
+            }
+            (Value::I64(i), TypeTag::U64) => {
+                Value::U64(u64::try_from(i).map_err(|_| out_of_range())?)
+            }
+            (Value::U64(u), TypeTag::F64) => Value::F64(u as f64),
+            (Value::I64(i), TypeTag::F64) => Value::F64(i as f64),
+            (Value::F64(f), TypeTag::I64) if (-I64_LIMIT..I64_LIMIT).contains(&f.trunc()) => {
+                Value::I64(f as i64)
+            }
+            (Value::F64(f), TypeTag::U64) if (0.0..U64_LIMIT).contains(&f.trunc()) => {
+                Value::U64(f as u64)
+            }
+            (Value::F64(_), TypeTag::I64 | TypeTag::U64) => return Err(out_of_range()),
+            (Value::Char(c), TypeTag::U64) => Value::U64(u64::from(u32::from(c))),
+            (Value::Char(c), TypeTag::I64) => Value::I64(i64::from(u32::from(c))),
+            (Value::U64(u), TypeTag::Char) => u32::try_from(u)
+                .ok()
+                .and_then(char::from_u32)
+                .map(Value::Char)
+                .ok_or_else(invalid_char)?,
+            (Value::I64(i), TypeTag::Char) => u32::try_from(i)
+                .ok()
+                .and_then(char::from_u32)
+                .map(Value::Char)
+                .ok_or_else(invalid_char)?,
+            (Value::Bool(b), TypeTag::U64) => Value::U64(u64::from(b)),
+            (Value::Bool(b), TypeTag::I64) => Value::I64(i64::from(b)),
+            (Value::U64(u), TypeTag::Bool) => Value::Bool(u != 0),
+            (Value::I64(i), TypeTag::Bool) => Value::Bool(i != 0),
+            _ => {
+                return Err(format!(
+                    "Can not cast a value of type '{}' to '{}'",
+                    self.type_info(),
+                    to.name()
+                ))
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Combines two 32-bit halves into a u64 value, `hi` being placed in the upper 32 bits.
+    pub fn pack(hi: Value, lo: Value) -> Result<Value, String> {
+        let hi = hi.as_half("hi")?;
+        let lo = lo.as_half("lo")?;
+
+        Ok(Value::U64(hi << 32 | lo))
+    }
+
+    /// Returns the upper 32 bits of an integer as a u64 value.
+    pub fn unpack_hi(self) -> Result<Value, String> {
+        self.as_bits("unpack_hi").map(|bits| Value::U64(bits >> 32))
+    }
+
+    /// Returns the lower 32 bits of an integer as a u64 value.
+    pub fn unpack_lo(self) -> Result<Value, String> {
+        self.as_bits("unpack_lo")
+            .map(|bits| Value::U64(bits & u64::from(u32::MAX)))
+    }
+
+    fn as_half(self, name: &str) -> Result<u64, String> {
+        let half = match self {
+            Value::U64(val) => Some(val),
+            Value::I64(val) => u64::try_from(val).ok(),
+            val => {
+                return Err(format!(
+                    "Intrinsic 'pack' not supported for value of type {}",
+                    val.type_info()
+                ))
+            }
+        };
+
+        match half {
+            Some(half) if half <= u64::from(u32::MAX) => Ok(half),
+            _ => Err(format!(
+                "Value {} does not fit in the 32 bits of the '{}' half of 'pack'",
+                self, name
+            )),
+        }
+    }
+
+    fn as_bits(self, intrinsic: &str) -> Result<u64, String> {
+        match self {
+            Value::U64(val) => Ok(val),
+            Value::I64(val) if val >= 0 => Ok(val as u64),
+            Value::I64(val) => Err(format!(
+                "Intrinsic '{}' not supported for negative value {}",
+                intrinsic, val
+            )),
+            val => Err(format!(
+                "Intrinsic '{}' not supported for value of type {}",
+                intrinsic,
+                val.type_info()
+            )),
+        }
+    }
+
+    /// Converts two numbers of different types to a common type before an arithmetic operator or
+    /// a comparison is applied to them. Mixing a float with an integer converts the integer to
+    /// `f64`, which may round integers beyond 2^53. Mixing `u64` with `i64` converts the `u64` to
+    /// `i64`, an error if it is too large to fit. Any other pair of values is returned unchanged,
+    /// so mixing numbers with booleans, characters or addresses is still rejected by the operator.
+    pub fn promote(a: Value, b: Value) -> Result<(Value, Value), String> {
+        let to_i64 = |u: u64| {
+            i64::try_from(u).map(Value::I64).map_err(|_| {
+                format!(
+                    "Value {} of type 'u64' does not fit in 'i64' to be mixed with an 'i64'",
+                    u
+                )
+            })
+        };
+
+        match (a, b) {
+            (Value::F64(_), Value::U64(b)) => Ok((a, Value::F64(b as f64))),
+            (Value::F64(_), Value::I64(b)) => Ok((a, Value::F64(b as f64))),
+            (Value::U64(a), Value::F64(_)) => Ok((Value::F64(a as f64), b)),
+            (Value::I64(a), Value::F64(_)) => Ok((Value::F64(a as f64), b)),
+            (Value::U64(a), Value::I64(_)) => Ok((to_i64(a)?, b)),
+            (Value::I64(_), Value::U64(b)) => Ok((a, to_i64(b)?)),
+            _ => Ok((a, b)),
+        }
+    }
+
     pub fn lt(a: Value, b: Value) -> Result<Value, String> {
+        let (a, b) = Value::promote(a, b)?;
+
         match (a, b) {
             (Value::F64(a), Value::F64(b)) => Ok(Value::Bool(a < b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::Bool(a < b)),
             (Value::I64(a), Value::I64(b)) => Ok(Value::Bool(a < b)),
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(!a & b)),
             (Value::Char(a), Value::Char(b)) => Ok(Value::Bool(a < b)),
+            (Value::Addr(a), Value::Addr(b)) => Ok(Value::Bool(a < b)),
             (a, b) => Err(format!(
                 "Operator '<' not supported between values of type '{}' and '{}'",
                 a.type_info(),
@@ -60,11 +401,15 @@ impl Value {
     }
 
     pub fn gt(a: Value, b: Value) -> Result<Value, String> {
+        let (a, b) = Value::promote(a, b)?;
+
         match (a, b) {
             (Value::F64(a), Value::F64(b)) => Ok(Value::Bool(a > b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::Bool(a > b)),
             (Value::I64(a), Value::I64(b)) => Ok(Value::Bool(a > b)),
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a & !b)),
             (Value::Char(a), Value::Char(b)) => Ok(Value::Bool(a > b)),
+            (Value::Addr(a), Value::Addr(b)) => Ok(Value::Bool(a > b)),
             (a, b) => Err(format!(
                 "Operator '>' not supported between values of type '{}' and '{}'",
                 a.type_info(),
@@ -73,13 +418,43 @@ impl Value {
         }
     }
 
+    /// Raises `a` to the power of `b`. Integers wrap around on overflow like the other
+    /// arithmetic operators, and can not be raised to negative exponents. Floats may be raised to
+    /// float or integer exponents.
+    pub fn pow(a: Value, b: Value) -> Result<Value, String> {
+        match (a, b) {
+            (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a.powf(b))),
+            (Value::F64(a), Value::I64(b)) => Ok(Value::F64(match i32::try_from(b) {
+                Ok(b) => a.powi(b),
+                Err(_) => a.powf(b as f64),
+            })),
+            (Value::U64(a), Value::U64(b)) => Ok(Value::U64(wrapping_pow(a, b))),
+            (Value::I64(_), Value::I64(b)) if b < 0 => Err(format!(
+                "Integers can not be raised to the negative exponent {}",
+                b
+            )),
+            (Value::I64(a), Value::I64(b)) => {
+                Ok(Value::I64(wrapping_pow(a as u64, b as u64) as i64))
+            }
+            (a, b) => Err(format!(
+                "Operator '**' not supported between values of type '{}' and '{}'",
+                a.type_info(),
+                b.type_info()
+            )),
+        }
+    }
+
     pub fn eq(a: Value, b: Value) -> Result<Value, String> {
+        let (a, b) = Value::promote(a, b)?;
+
         match (a, b) {
             (Value::F64(a), Value::F64(b)) => Ok(Value::Bool(a == b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::Bool(a == b)),
             (Value::I64(a), Value::I64(b)) => Ok(Value::Bool(a == b)),
             (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a == b)),
             (Value::Char(a), Value::Char(b)) => Ok(Value::Bool(a == b)),
+            (Value::Addr(a), Value::Addr(b)) => Ok(Value::Bool(a == b)),
+            (Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a == b)),
             (a, b) => Err(format!(
                 "Operator '==' not supported between values of type '{}' and '{}'",
                 a.type_info(),
@@ -88,6 +463,95 @@ impl Value {
         }
     }
 
+    /// Zero value of the same type, `false` for booleans and `'\0'` for characters.
+    pub fn zero(&self) -> Value {
+        match self {
+            Value::F64(_) => Value::F64(0.0),
+            Value::U64(_) => Value::U64(0),
+            Value::I64(_) => Value::I64(0),
+            Value::Bool(_) => Value::Bool(false),
+            Value::Char(_) => Value::Char('\0'),
+            Value::Addr(_) => Value::Addr(0),
+            Value::Str(_) => Value::Str(0),
+        }
+    }
+
+    /// Restricts a number to the range `[lo, hi]`, all three of the same type. Clamping NaN
+    /// gives NaN, while NaN bounds are an error, as are bounds where `lo > hi`.
+    pub fn clamp(self, lo: Value, hi: Value) -> Result<Value, String> {
+        fn check_bounds<T: PartialOrd + Display>(lo: T, hi: T) -> Result<(), String> {
+            match lo.partial_cmp(&hi) {
+                Some(Ordering::Less | Ordering::Equal) => Ok(()),
+                Some(Ordering::Greater) => Err(format!(
+                    "Lower bound {} of 'clamp' is greater than upper bound {}",
+                    lo, hi
+                )),
+                None => Err("Bounds of 'clamp' can not be NaN".into()),
+            }
+        }
+
+        match (self, lo, hi) {
+            (Value::F64(val), Value::F64(lo), Value::F64(hi)) => {
+                check_bounds(lo, hi).map(|_| Value::F64(val.clamp(lo, hi)))
+            }
+            (Value::U64(val), Value::U64(lo), Value::U64(hi)) => {
+                check_bounds(lo, hi).map(|_| Value::U64(val.clamp(lo, hi)))
+            }
+            (Value::I64(val), Value::I64(lo), Value::I64(hi)) => {
+                check_bounds(lo, hi).map(|_| Value::I64(val.clamp(lo, hi)))
+            }
+            (val, lo, hi) => Err(format!(
+                "Intrinsic 'clamp' not supported between values of type '{}', '{}' and '{}'",
+                val.type_info(),
+                lo.type_info(),
+                hi.type_info()
+            )),
+        }
+    }
+
+    /// 64-bit FNV-1a hash of the type and contents of a sequence of values. Numbers are hashed
+    /// through their little-endian bytes, so the result is the same on every platform.
+    pub fn checksum(values: &[Value]) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        values
+            .iter()
+            .flat_map(|value| {
+                let (tag, bits) = value.tag_and_bits();
+                std::iter::once(tag).chain(bits.to_le_bytes())
+            })
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Tag of the type and raw bits of the value, which together identify it. Floats are
+    /// represented by their bit pattern, so `0.0` and `-0.0` differ while equal NaNs do not.
+    pub fn tag_and_bits(&self) -> (u8, u64) {
+        match *self {
+            Value::F64(val) => (0, val.to_bits()),
+            Value::U64(val) => (1, val),
+            Value::I64(val) => (2, val as u64),
+            Value::Bool(val) => (3, val as u64),
+            Value::Char(val) => (4, val as u64),
+            Value::Addr(val) => (5, val as u64),
+            Value::Str(id) => (6, u64::from(id)),
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_int() || self.is_float()
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, Value::U64(_) | Value::I64(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Value::F64(_))
+    }
+
     pub fn is_numeric_zero(&self) -> bool {
         match *self {
             Value::F64(v) if v == 0.0 || v == -0.0 => true,
@@ -104,19 +568,50 @@ impl Value {
             Value::Bool(_) => "bool",
             Value::Char(_) => "char",
             Value::Addr(_) => "addr",
+            Value::Str(_) => "str",
+        }
+    }
+}
+
+/// Exponentiation by squaring with wrapping multiplications, which give the same bits for signed
+/// and unsigned operands.
+fn wrapping_pow(mut base: u64, mut exp: u64) -> u64 {
+    let mut result: u64 = 1;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.wrapping_mul(base);
         }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
     }
+
+    result
 }
 
+/// The alternate form, `{:#}`, annotates the value with its type: numbers are written with their
+/// type suffix, such as `5i64`, and other values are followed by their type, such as `true:bool`.
+/// Strings only hold the id of their text and are shown as `str(id)`, `Chunk::display_value`
+/// shows their text.
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self {
-            Value::F64(val) => write!(f, "{}", val),
+            Value::F64(val) => {
+                let style = crate::FLOAT_STYLE.read().unwrap();
+                write!(f, "{}", style.format(*val))
+            }
             Value::U64(val) => write!(f, "{}", val),
             Value::I64(val) => write!(f, "{}", val),
             Value::Bool(val) => write!(f, "{}", val),
             Value::Char(val) => write!(f, "{}", val),
             Value::Addr(val) => write!(f, "addr({})", val),
+            Value::Str(id) => write!(f, "str({})", id),
+        }?;
+
+        match self {
+            _ if !f.alternate() => Ok(()),
+            Value::F64(_) | Value::U64(_) | Value::I64(_) => write!(f, "{}", self.type_info()),
+            _ => write!(f, ":{}", self.type_info()),
         }
     }
 }
@@ -125,8 +620,7 @@ impl ops::Add<Value> for Value {
     type Output = Result<Value, String>;
 
     fn add(self, rhs: Value) -> Result<Value, String> {
-        match (self, rhs) {
-            // TODO: handle warnings/errors in regard to overflow
+        match Value::promote(self, rhs)? {
             (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a + b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a.wrapping_add(b))),
             (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a.wrapping_add(b))),
@@ -143,8 +637,7 @@ impl ops::Sub<Value> for Value {
     type Output = Result<Value, String>;
 
     fn sub(self, rhs: Value) -> Result<Value, String> {
-        match (self, rhs) {
-            // TODO: handle warnings/errors in regard to overflow
+        match Value::promote(self, rhs)? {
             (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a - b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a.wrapping_sub(b))),
             (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a.wrapping_sub(b))),
@@ -161,8 +654,7 @@ impl ops::Mul<Value> for Value {
     type Output = Result<Value, String>;
 
     fn mul(self, rhs: Value) -> Result<Value, String> {
-        match (self, rhs) {
-            // TODO: handle warnings/errors in regard to overflow
+        match Value::promote(self, rhs)? {
             (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a * b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a.wrapping_mul(b))),
             (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a.wrapping_mul(b))),
@@ -179,14 +671,13 @@ impl ops::Div<Value> for Value {
     type Output = Result<Value, String>;
 
     fn div(self, rhs: Value) -> Result<Value, String> {
-        match (self, rhs) {
-            // TODO: handle warnings/errors in regard to overflow
+        match Value::promote(self, rhs)? {
             (Value::F64(_) | Value::I64(_) | Value::U64(_), b) if b.is_numeric_zero() => {
                 Err("Division by 0".to_string())
             }
             (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a / b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a / b)),
-            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a / b)),
+            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a.wrapping_div(b))),
             (a, b) => Err(format!(
                 "Operator '/' not supported between values of type '{}' and '{}'",
                 a.type_info(),
@@ -200,13 +691,13 @@ impl ops::Rem<Value> for Value {
     type Output = Result<Value, String>;
 
     fn rem(self, rhs: Value) -> Result<Value, String> {
-        match (self, rhs) {
+        match Value::promote(self, rhs)? {
             (Value::F64(_) | Value::I64(_) | Value::U64(_), b) if b.is_numeric_zero() => {
                 Err("Division by 0".to_string())
             }
             (Value::F64(a), Value::F64(b)) => Ok(Value::F64(a % b)),
             (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a % b)),
-            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a % b)),
+            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a.wrapping_rem(b))),
             (a, b) => Err(format!(
                 "Operator '%' not supported between values of type '{}' and '{}'",
                 a.type_info(),
@@ -227,7 +718,7 @@ impl ops::Shl<Value> for Value {
             }
             (Value::U64(a), Value::I64(b)) => {
                 if b < 0 {
-                    return Value::U64(a).shr(Value::I64(-b));
+                    return Value::U64(a).shr(Value::I64(b.checked_neg().unwrap_or(i64::MAX)));
                 }
                 let b = b.try_into().unwrap_or(u32::MAX);
 
@@ -235,7 +726,7 @@ impl ops::Shl<Value> for Value {
             }
             (Value::I64(a), Value::I64(b)) => {
                 if b < 0 {
-                    return Value::I64(a).shr(Value::I64(-b));
+                    return Value::I64(a).shr(Value::I64(b.checked_neg().unwrap_or(i64::MAX)));
                 }
                 let b = b.try_into().unwrap_or(u32::MAX);
 
@@ -250,6 +741,54 @@ impl ops::Shl<Value> for Value {
     }
 }
 
+impl ops::BitAnd<Value> for Value {
+    type Output = Result<Value, String>;
+
+    fn bitand(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a & b)),
+            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a & b)),
+            (a, b) => Err(format!(
+                "Operator '&' not supported between values of type '{}' and '{}'",
+                a.type_info(),
+                b.type_info()
+            )),
+        }
+    }
+}
+
+impl ops::BitOr<Value> for Value {
+    type Output = Result<Value, String>;
+
+    fn bitor(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a | b)),
+            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a | b)),
+            (a, b) => Err(format!(
+                "Operator '|' not supported between values of type '{}' and '{}'",
+                a.type_info(),
+                b.type_info()
+            )),
+        }
+    }
+}
+
+impl ops::BitXor<Value> for Value {
+    type Output = Result<Value, String>;
+
+    fn bitxor(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::U64(a), Value::U64(b)) => Ok(Value::U64(a ^ b)),
+            (Value::I64(a), Value::I64(b)) => Ok(Value::I64(a ^ b)),
+            (a, b) => Err(format!(
+                "Operator '^' not supported between values of type '{}' and '{}'",
+                a.type_info(),
+                b.type_info()
+            )),
+        }
+    }
+}
+
 impl ops::Shr<Value> for Value {
     type Output = Result<Value, String>;
 
@@ -261,7 +800,7 @@ impl ops::Shr<Value> for Value {
             }
             (Value::U64(a), Value::I64(b)) => {
                 if b < 0 {
-                    return Value::U64(a).shl(Value::I64(-b));
+                    return Value::U64(a).shl(Value::I64(b.checked_neg().unwrap_or(i64::MAX)));
                 }
                 let b = b.try_into().unwrap_or(u32::MAX);
 
@@ -269,14 +808,14 @@ impl ops::Shr<Value> for Value {
             }
             (Value::I64(a), Value::I64(b)) => {
                 if b < 0 {
-                    return Value::I64(a).shl(Value::I64(-b));
+                    return Value::I64(a).shl(Value::I64(b.checked_neg().unwrap_or(i64::MAX)));
                 }
                 let b = b.try_into().unwrap_or(u32::MAX);
 
                 Ok(Value::I64(a.wrapping_shr(b)))
             }
             (a, b) => Err(format!(
-                "Operator '<<' not supported between values of type '{}' and '{}'",
+                "Operator '>>' not supported between values of type '{}' and '{}'",
                 a.type_info(),
                 b.type_info()
             )),
@@ -288,10 +827,439 @@ impl ops::Shr<Value> for Value {
 mod test {
     use std::mem::size_of;
 
-    use crate::value::Value;
+    use crate::value::{FloatStyle, TypeTag, Value};
 
     #[test]
     fn value_is_at_most_128_bits() {
         assert!(size_of::<Value>() <= 16);
     }
+
+    #[test]
+    fn special_floats_in_c_style() {
+        assert_eq!(FloatStyle::C.format(-0.0), "-0");
+        assert_eq!(FloatStyle::C.format(f64::INFINITY), "inf");
+        assert_eq!(FloatStyle::C.format(f64::NEG_INFINITY), "-inf");
+        assert_eq!(FloatStyle::C.format(f64::NAN), "nan");
+        assert_eq!(FloatStyle::C.format(2.5), "2.5");
+    }
+
+    #[test]
+    fn special_floats_in_js_style() {
+        assert_eq!(FloatStyle::Js.format(-0.0), "0");
+        assert_eq!(FloatStyle::Js.format(f64::INFINITY), "Infinity");
+        assert_eq!(FloatStyle::Js.format(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(FloatStyle::Js.format(f64::NAN), "NaN");
+        assert_eq!(FloatStyle::Js.format(2.5), "2.5");
+    }
+
+    #[test]
+    fn parse_each_type() {
+        assert!(matches!(
+            Value::parse(" 42\n", TypeTag::I64),
+            Ok(Value::I64(42))
+        ));
+        assert!(matches!(
+            Value::parse("42", TypeTag::U64),
+            Ok(Value::U64(42))
+        ));
+        assert!(matches!(Value::parse("-2.5", TypeTag::F64), Ok(Value::F64(v)) if v == -2.5));
+        assert!(matches!(
+            Value::parse("true\r\n", TypeTag::Bool),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::parse("é", TypeTag::Char),
+            Ok(Value::Char('é'))
+        ));
+        assert_eq!(
+            Value::parse("-1", TypeTag::U64).unwrap_err(),
+            "Could not parse '-1' as a value of type u64"
+        );
+        assert!(matches!(
+            Value::parse(" \r\n", TypeTag::Char),
+            Ok(Value::Char(' '))
+        ));
+        assert!(Value::parse("ab", TypeTag::Char).is_err());
+        assert!(Value::parse("", TypeTag::I64).is_err());
+    }
+
+    #[test]
+    fn annotated_display_of_each_type() {
+        assert_eq!(format!("{:#}", Value::F64(2.5)), "2.5f64");
+        assert_eq!(format!("{:#}", Value::U64(7)), "7u64");
+        assert_eq!(format!("{:#}", Value::I64(-5)), "-5i64");
+        assert_eq!(format!("{:#}", Value::Bool(true)), "true:bool");
+        assert_eq!(format!("{:#}", Value::Char('a')), "a:char");
+        assert_eq!(format!("{:#}", Value::Addr(3)), "addr(3):addr");
+        assert_eq!(format!("{}", Value::I64(-5)), "-5");
+    }
+
+    #[test]
+    fn negative_shifts_go_the_other_way() {
+        assert!(matches!(Value::I64(1) << Value::I64(4), Ok(Value::I64(16))));
+        assert!(matches!(
+            Value::I64(16) << Value::I64(-2),
+            Ok(Value::I64(4))
+        ));
+        assert!(matches!(
+            Value::I64(16) >> Value::I64(-2),
+            Ok(Value::I64(64))
+        ));
+        assert!(matches!(
+            Value::U64(16) << Value::I64(-4),
+            Ok(Value::U64(1))
+        ));
+        assert!(matches!(Value::U64(1) >> Value::I64(-3), Ok(Value::U64(8))));
+        assert!((Value::I64(1) << Value::I64(i64::MIN)).is_ok());
+    }
+
+    #[test]
+    fn smallest_i64_divided_by_minus_one_wraps_around() {
+        let min = Value::I64(i64::MIN);
+
+        assert!(matches!(min / Value::I64(-1), Ok(Value::I64(i64::MIN))));
+        assert!(matches!(min % Value::I64(-1), Ok(Value::I64(0))));
+    }
+
+    #[test]
+    fn powers_of_integers_and_floats() {
+        assert!(matches!(
+            Value::pow(Value::I64(-3), Value::I64(3)),
+            Ok(Value::I64(-27))
+        ));
+        assert!(matches!(
+            Value::pow(Value::U64(2), Value::U64(10)),
+            Ok(Value::U64(1024))
+        ));
+        assert!(matches!(
+            Value::pow(Value::U64(2), Value::U64(64)),
+            Ok(Value::U64(0))
+        ));
+        assert!(matches!(
+            Value::pow(Value::I64(1), Value::I64(i64::MAX)),
+            Ok(Value::I64(1))
+        ));
+        assert!(matches!(
+            Value::pow(Value::F64(2.0), Value::I64(-1)),
+            Ok(Value::F64(v)) if v == 0.5
+        ));
+        assert!(matches!(
+            Value::pow(Value::F64(4.0), Value::F64(0.5)),
+            Ok(Value::F64(v)) if v == 2.0
+        ));
+
+        assert_eq!(
+            Value::pow(Value::I64(2), Value::I64(-1)).unwrap_err(),
+            "Integers can not be raised to the negative exponent -1"
+        );
+        assert!(Value::pow(Value::I64(2), Value::F64(1.0)).is_err());
+    }
+
+    #[test]
+    fn shifts_between_incompatible_types_name_the_operator() {
+        assert_eq!(
+            (Value::F64(1.0) << Value::I64(1)).unwrap_err(),
+            "Operator '<<' not supported between values of type 'f64' and 'i64'"
+        );
+        assert_eq!(
+            (Value::I64(1) >> Value::U64(1)).unwrap_err(),
+            "Operator '>>' not supported between values of type 'i64' and 'u64'"
+        );
+    }
+
+    #[test]
+    fn bitwise_and_of_integers() {
+        assert!(matches!(
+            Value::U64(0xF0) & Value::U64(0x0F),
+            Ok(Value::U64(0))
+        ));
+        assert!(matches!(
+            Value::I64(-1) & Value::I64(0x3C),
+            Ok(Value::I64(0x3C))
+        ));
+        assert_eq!(
+            (Value::F64(1.0) & Value::F64(1.0)).unwrap_err(),
+            "Operator '&' not supported between values of type 'f64' and 'f64'"
+        );
+        assert!((Value::Bool(true) & Value::Bool(true)).is_err());
+        assert!((Value::U64(1) & Value::I64(1)).is_err());
+    }
+
+    #[test]
+    fn bitwise_or_and_xor_of_integers() {
+        assert!(matches!(
+            Value::U64(0xF0) | Value::U64(0x0F),
+            Ok(Value::U64(0xFF))
+        ));
+        assert!(matches!(
+            Value::I64(0b1100) ^ Value::I64(0b1010),
+            Ok(Value::I64(0b0110))
+        ));
+        assert!(matches!(Value::I64(-1) ^ Value::I64(-1), Ok(Value::I64(0))));
+        assert_eq!(
+            (Value::F64(1.0) | Value::F64(2.0)).unwrap_err(),
+            "Operator '|' not supported between values of type 'f64' and 'f64'"
+        );
+        assert_eq!(
+            (Value::Bool(true) ^ Value::Bool(false)).unwrap_err(),
+            "Operator '^' not supported between values of type 'bool' and 'bool'"
+        );
+    }
+
+    #[test]
+    fn addresses_compare_by_stack_slot() {
+        assert!(matches!(
+            Value::eq(Value::Addr(3), Value::Addr(3)),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::eq(Value::Addr(3), Value::Addr(4)),
+            Ok(Value::Bool(false))
+        ));
+        assert!(matches!(
+            Value::lt(Value::Addr(3), Value::Addr(4)),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::gt(Value::Addr(3), Value::Addr(4)),
+            Ok(Value::Bool(false))
+        ));
+        assert!(Value::eq(Value::Addr(3), Value::U64(3)).is_err());
+        assert!(Value::lt(Value::I64(3), Value::Addr(4)).is_err());
+    }
+
+    #[test]
+    fn clamp_below_within_and_above_range() {
+        let (lo, hi) = (Value::I64(-2), Value::I64(5));
+
+        assert!(matches!(Value::I64(-7).clamp(lo, hi), Ok(Value::I64(-2))));
+        assert!(matches!(Value::I64(3).clamp(lo, hi), Ok(Value::I64(3))));
+        assert!(matches!(Value::I64(9).clamp(lo, hi), Ok(Value::I64(5))));
+        assert!(matches!(
+            Value::U64(9).clamp(Value::U64(1), Value::U64(1)),
+            Ok(Value::U64(1))
+        ));
+
+        let (lo, hi) = (Value::F64(0.0), Value::F64(1.0));
+        assert!(matches!(Value::F64(-0.5).clamp(lo, hi), Ok(Value::F64(v)) if v == 0.0));
+        assert!(matches!(Value::F64(0.5).clamp(lo, hi), Ok(Value::F64(v)) if v == 0.5));
+        assert!(matches!(Value::F64(1.5).clamp(lo, hi), Ok(Value::F64(v)) if v == 1.0));
+        assert!(matches!(Value::F64(f64::NAN).clamp(lo, hi), Ok(Value::F64(v)) if v.is_nan()));
+    }
+
+    #[test]
+    fn clamp_rejects_invalid_bounds_and_types() {
+        assert!(Value::I64(1).clamp(Value::I64(5), Value::I64(2)).is_err());
+        assert!(Value::F64(1.0)
+            .clamp(Value::F64(f64::NAN), Value::F64(2.0))
+            .is_err());
+        assert!(Value::I64(1).clamp(Value::U64(0), Value::I64(2)).is_err());
+        assert!(Value::Char('a')
+            .clamp(Value::Char('a'), Value::Char('z'))
+            .is_err());
+    }
+
+    #[test]
+    fn checksum_is_stable() {
+        // FNV-1a of no bytes is the offset basis
+        assert_eq!(Value::checksum(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(
+            Value::checksum(&[Value::I64(1)]),
+            Value::checksum(&[Value::U64(1)])
+        );
+    }
+
+    #[test]
+    fn numeric_type_checks_of_each_type() {
+        let values = [
+            (Value::F64(1.5), true, false, true),
+            (Value::U64(1), true, true, false),
+            (Value::I64(-1), true, true, false),
+            (Value::Bool(true), false, false, false),
+            (Value::Char('1'), false, false, false),
+            (Value::Addr(1), false, false, false),
+        ];
+
+        for (value, numeric, int, float) in values {
+            assert_eq!(value.is_numeric(), numeric, "{:#}", value);
+            assert_eq!(value.is_int(), int, "{:#}", value);
+            assert_eq!(value.is_float(), float, "{:#}", value);
+        }
+    }
+
+    #[test]
+    fn case_conversion_of_chars() {
+        assert!(matches!(
+            Value::Char('a').to_uppercase(),
+            Ok(Value::Char('A'))
+        ));
+        assert!(matches!(
+            Value::Char('A').to_lowercase(),
+            Ok(Value::Char('a'))
+        ));
+        assert!(matches!(
+            Value::Char('7').to_uppercase(),
+            Ok(Value::Char('7'))
+        ));
+        assert!(matches!(
+            Value::Char('ß').to_uppercase(),
+            Ok(Value::Char('ß'))
+        ));
+        assert!(Value::I64(97).to_uppercase().is_err());
+    }
+
+    #[test]
+    fn casts_between_numeric_types() {
+        assert!(matches!(Value::I64(-3).cast(TypeTag::F64), Ok(Value::F64(f)) if f == -3.0));
+        assert!(matches!(
+            Value::F64(2.9).cast(TypeTag::I64),
+            Ok(Value::I64(2))
+        ));
+        assert!(matches!(
+            Value::F64(-2.9).cast(TypeTag::I64),
+            Ok(Value::I64(-2))
+        ));
+        assert!(matches!(
+            Value::F64(-0.5).cast(TypeTag::U64),
+            Ok(Value::U64(0))
+        ));
+        assert!(
+            matches!(Value::U64(1 << 63).cast(TypeTag::F64), Ok(Value::F64(f)) if f == 9_223_372_036_854_775_808.0)
+        );
+        assert!(matches!(
+            Value::I64(i64::MIN)
+                .cast(TypeTag::F64)
+                .and_then(|f| f.cast(TypeTag::I64)),
+            Ok(Value::I64(i64::MIN))
+        ));
+        assert!(matches!(
+            Value::Char('a').cast(TypeTag::U64),
+            Ok(Value::U64(97))
+        ));
+        assert!(matches!(
+            Value::I64(955).cast(TypeTag::Char),
+            Ok(Value::Char('λ'))
+        ));
+        assert!(matches!(
+            Value::U64(2).cast(TypeTag::Bool),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::Bool(true).cast(TypeTag::I64),
+            Ok(Value::I64(1))
+        ));
+    }
+
+    #[test]
+    fn impossible_casts_are_errors() {
+        assert!(Value::I64(-1).cast(TypeTag::U64).is_err());
+        assert!(Value::U64(u64::MAX).cast(TypeTag::I64).is_err());
+        assert!(Value::F64(9_223_372_036_854_775_808.0)
+            .cast(TypeTag::I64)
+            .is_err());
+        assert!(Value::F64(-1.0).cast(TypeTag::U64).is_err());
+        assert!(Value::F64(f64::NAN).cast(TypeTag::I64).is_err());
+        assert!(Value::F64(f64::INFINITY).cast(TypeTag::U64).is_err());
+        assert!(Value::I64(0xd800).cast(TypeTag::Char).is_err());
+        assert!(Value::F64(1.0).cast(TypeTag::Bool).is_err());
+        assert_eq!(
+            Value::Addr(0).cast(TypeTag::U64).unwrap_err(),
+            "Can not cast a value of type 'addr' to 'u64'"
+        );
+    }
+
+    #[test]
+    fn pack_and_unpack_halves() {
+        let packed = Value::pack(Value::U64(0x1234_5678), Value::I64(0x9abc_def0)).unwrap();
+
+        assert!(matches!(packed, Value::U64(0x1234_5678_9abc_def0)));
+        assert!(matches!(packed.unpack_hi(), Ok(Value::U64(0x1234_5678))));
+        assert!(matches!(packed.unpack_lo(), Ok(Value::U64(0x9abc_def0))));
+    }
+
+    #[test]
+    fn pack_rejects_invalid_halves() {
+        assert!(Value::pack(Value::U64(1 << 32), Value::U64(0)).is_err());
+        assert!(Value::pack(Value::U64(0), Value::I64(-1)).is_err());
+        assert!(Value::pack(Value::Bool(true), Value::U64(0)).is_err());
+        assert!(Value::F64(1.0).unpack_hi().is_err());
+    }
+
+    #[test]
+    fn strings_support_equality_but_not_arithmetic() {
+        assert!(matches!(
+            Value::eq(Value::Str(1), Value::Str(1)),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::eq(Value::Str(1), Value::Str(2)),
+            Ok(Value::Bool(false))
+        ));
+        assert_eq!(format!("{}", Value::Str(3)), "str(3)");
+
+        assert_eq!(
+            (Value::Str(0) + Value::Str(1)).unwrap_err(),
+            "Operator '+' not supported between values of type 'str' and 'str'"
+        );
+        assert!((Value::Str(0) * Value::U64(2)).is_err());
+        assert!(Value::Str(0).arithmetic_negate().is_err());
+    }
+
+    #[test]
+    fn mixed_numbers_are_promoted_to_a_common_type() {
+        assert!(matches!(Value::I64(-2) + Value::F64(0.5), Ok(Value::F64(f)) if f == -1.5));
+        assert!(matches!(Value::F64(7.5) - Value::U64(2), Ok(Value::F64(f)) if f == 5.5));
+        assert!(matches!(Value::U64(3) * Value::I64(-2), Ok(Value::I64(-6))));
+        assert!(matches!(Value::I64(-7) / Value::U64(2), Ok(Value::I64(-3))));
+        assert!(matches!(Value::U64(7) % Value::F64(2.0), Ok(Value::F64(f)) if f == 1.0));
+
+        assert!(matches!(
+            Value::lt(Value::I64(-1), Value::U64(0)),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::gt(Value::U64(2), Value::F64(1.5)),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            Value::eq(Value::F64(3.0), Value::I64(3)),
+            Ok(Value::Bool(true))
+        ));
+    }
+
+    #[test]
+    fn mixed_values_that_can_not_be_promoted_are_errors() {
+        assert_eq!(
+            (Value::U64(u64::MAX) + Value::I64(1)).unwrap_err(),
+            "Value 18446744073709551615 of type 'u64' does not fit in 'i64' to be mixed with an 'i64'"
+        );
+        assert!(Value::lt(Value::I64(0), Value::U64(1 << 63)).is_err());
+        assert_eq!(
+            (Value::Bool(true) + Value::I64(1)).unwrap_err(),
+            "Operator '+' not supported between values of type 'bool' and 'i64'"
+        );
+        assert!((Value::Char('a') - Value::U64(1)).is_err());
+        assert!(Value::eq(Value::Addr(1), Value::U64(1)).is_err());
+    }
+
+    #[test]
+    fn booleans_are_ordered_with_false_first() {
+        let cases = [
+            (false, false, false, false),
+            (false, true, true, false),
+            (true, false, false, true),
+            (true, true, false, false),
+        ];
+
+        for (a, b, lt, gt) in cases {
+            let (a, b) = (Value::Bool(a), Value::Bool(b));
+            assert!(matches!(Value::lt(a, b), Ok(Value::Bool(r)) if r == lt));
+            assert!(matches!(Value::gt(a, b), Ok(Value::Bool(r)) if r == gt));
+        }
+
+        assert_eq!(
+            Value::lt(Value::Bool(true), Value::I64(1)).unwrap_err(),
+            "Operator '<' not supported between values of type 'bool' and 'i64'"
+        );
+    }
 }
@@ -1,17 +1,41 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    convert::TryInto,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    convert::{TryFrom, TryInto},
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use crate::{
     chunk::{Chunk, Instruction},
     compiler::Compiler,
-    error::{TACError, TACResult},
-    value::Value,
+    error::{did_you_mean, TACError, TACResult},
+    intrinsic::Intrinsic,
+    optimizer, typecheck,
+    value::{ArithmeticMode, TypeTag, Value},
 };
 
 type SymbolTable = HashMap<u16, usize>;
 
+/// Value returned by the `steps_left` intrinsic when there is no limit.
+const UNLIMITED: u64 = u64::MAX;
+
+/// Number of values the stack holds unless set with `with_stack_limit`.
+const DEFAULT_STACK_LIMIT: usize = 1 << 20;
+
+/// Number of nested calls allowed unless set with `with_depth_limit`.
+const DEFAULT_DEPTH_LIMIT: usize = 4096;
+
+/// Number of instructions executed between checks of the timeout unless set with
+/// `with_timeout_interval`. Reading the clock is much slower than running an instruction.
+const DEFAULT_TIMEOUT_INTERVAL: u64 = 1024;
+
+/// Largest width accepted by `printw`.
+const MAX_PRINT_WIDTH: usize = 1 << 16;
+
 #[derive(Default, Debug)]
 pub struct Frame {
     st: SymbolTable,
@@ -19,11 +43,72 @@ pub struct Frame {
     rsp: usize,
 }
 
+impl Frame {
+    /// Stack address of each variable of the frame, keyed by the address of its name.
+    pub fn variables(&self) -> &SymbolTable {
+        &self.st
+    }
+
+    /// Address of the instruction the frame returns to, `None` for the main frame.
+    pub fn return_address(&self) -> Option<usize> {
+        self.ra
+    }
+}
+
+/// Outcome of executing a single instruction with `VirtualMachine::step`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepResult {
+    /// The program goes on with the instruction at `ip`.
+    Continue,
+    /// The program finished, halting or returning from the main frame.
+    Halted,
+    /// The next instruction starts a line with a breakpoint and was not executed yet. Stepping
+    /// again executes it.
+    Breakpoint,
+}
+
 pub struct VirtualMachine {
     chunk: Chunk,
     frames: Vec<Frame>,
     stack: Vec<Value>,
     ip: usize,
+    /// Index of the instruction being executed, whose line is reported by runtime errors.
+    current_ip: usize,
+    args: Vec<String>,
+    coverage: Option<Vec<bool>>,
+    /// Number of times each instruction was executed, keyed by its offset, when profiling.
+    profile: Option<HashMap<usize, u64>>,
+    /// Wall-clock time taken by the last program, when it is being timed.
+    elapsed: Option<Duration>,
+    interrupted: Arc<AtomicBool>,
+    /// Length of the arrays stored in the stack, keyed by the address of their first element.
+    arrays: HashMap<usize, usize>,
+    implicit_halt: bool,
+    /// Maximum number of instructions a program may execute, if limited.
+    step_limit: Option<u64>,
+    steps: u64,
+    /// Maximum time a program may run for, if limited.
+    timeout: Option<Duration>,
+    /// Number of instructions executed between checks of the timeout.
+    timeout_interval: u64,
+    /// When the running program times out.
+    deadline: Option<Instant>,
+    /// Maximum number of values in the stack, counting variables and data arrays.
+    stack_limit: usize,
+    /// Maximum number of calls that may be running at once.
+    depth_limit: usize,
+    /// Source of the lines read by `scan`, stdin unless set with `with_input`.
+    input: Option<Box<dyn BufRead>>,
+    typecheck: bool,
+    optimize: bool,
+    /// What integer operations that overflow give.
+    arithmetic: ArithmeticMode,
+    /// Value given to `return` by the last subroutine that returned.
+    result: Option<Value>,
+    /// Source lines where stepping pauses before executing them.
+    breakpoints: HashSet<usize>,
+    /// Whether the last step paused at a breakpoint, so that the next one goes past it.
+    paused: bool,
 }
 
 macro_rules! binary_op {
@@ -62,6 +147,12 @@ macro_rules! binary_op_f {
     }};
 }
 
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VirtualMachine {
     pub fn new() -> Self {
         Self {
@@ -69,18 +160,419 @@ impl VirtualMachine {
             frames: vec![],
             stack: vec![],
             ip: 0,
+            current_ip: 0,
+            args: vec![],
+            coverage: None,
+            profile: None,
+            elapsed: None,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            arrays: HashMap::new(),
+            implicit_halt: true,
+            step_limit: None,
+            steps: 0,
+            timeout: None,
+            timeout_interval: DEFAULT_TIMEOUT_INTERVAL,
+            deadline: None,
+            stack_limit: DEFAULT_STACK_LIMIT,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            input: None,
+            typecheck: false,
+            optimize: false,
+            arithmetic: ArithmeticMode::Wrap,
+            result: None,
+            breakpoints: HashSet::new(),
+            paused: false,
         }
     }
 
+    /// Sets the arguments that programs can read through the `argc` and `arg` intrinsics.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Records which instructions are executed, see `report_coverage`.
+    pub fn with_coverage(mut self, enabled: bool) -> Self {
+        self.coverage = if enabled { Some(vec![]) } else { None };
+        self
+    }
+
+    /// Counts how many times each instruction is executed, see `report_profile`.
+    pub fn with_profile(mut self, enabled: bool) -> Self {
+        self.profile = if enabled { Some(HashMap::new()) } else { None };
+        self
+    }
+
+    /// Measures how long programs take to run, see `report_time`.
+    pub fn with_timing(mut self, enabled: bool) -> Self {
+        self.elapsed = if enabled { Some(Duration::ZERO) } else { None };
+        self
+    }
+
+    /// Sets what integer additions, subtractions, multiplications, divisions and powers give when
+    /// their result does not fit in their type. They wrap around by default.
+    pub fn with_arithmetic(mut self, mode: ArithmeticMode) -> Self {
+        self.arithmetic = mode;
+        self
+    }
+
+    /// Applies the peephole rules of `optimizer::optimize` to the program before running it.
+    pub fn with_optimize(mut self, enabled: bool) -> Self {
+        self.optimize = enabled;
+        self
+    }
+
+    /// Checks the types of the program before running it, see `typecheck::check`. Programs with
+    /// type errors fail with a compile error.
+    pub fn with_typecheck(mut self, enabled: bool) -> Self {
+        self.typecheck = enabled;
+        self
+    }
+
+    /// Whether a `Halt` is added after the last statement of each source, enabled by default. See
+    /// `Compiler::compile` and `interpret_all`.
+    pub fn with_implicit_halt(mut self, enabled: bool) -> Self {
+        self.implicit_halt = enabled;
+        self
+    }
+
+    /// Stops programs with a runtime error once they execute more than `limit` instructions.
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Stops programs with a runtime error once they run for longer than `timeout`. The clock is
+    /// only read every few instructions, see `with_timeout_interval`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Checks the timeout once every `interval` instructions, 1024 by default. Smaller intervals
+    /// stop timed out programs sooner but slow every program down.
+    pub fn with_timeout_interval(mut self, interval: u64) -> Self {
+        self.timeout_interval = interval.max(1);
+        self
+    }
+
+    /// Stops programs with a runtime error once they would hold more than `limit` values in the
+    /// stack, instead of growing it until memory runs out. Defaults to 2^20 values.
+    pub fn with_stack_limit(mut self, limit: usize) -> Self {
+        self.stack_limit = limit;
+        self
+    }
+
+    /// Stops programs with a runtime error once a call would nest more than `limit` calls.
+    /// Defaults to 4096 calls.
+    pub fn with_depth_limit(mut self, limit: usize) -> Self {
+        self.depth_limit = limit;
+        self
+    }
+
+    /// Reads the input of `scan` from `input` instead of stdin.
+    pub fn with_input<R: BufRead + 'static>(mut self, input: R) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Flag that, once set, stops the running program with a runtime error. Used to interrupt
+    /// long running programs on Ctrl-C.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.interrupted)
+    }
+
+    /// Makes `step` and `resume` pause when the program is about to execute the given source
+    /// line. Breakpoints are ignored by `interpret`.
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    pub fn remove_breakpoint(&mut self, line: usize) {
+        self.breakpoints.remove(&line);
+    }
+
     pub fn interpret(&mut self, source: &str) -> TACResult<()> {
+        self.interpret_to(source, &mut io::stdout())
+    }
+
+    /// Same as `interpret`, but the output of the program is written to `out` instead of stdout.
+    pub fn interpret_to<W: Write>(&mut self, source: &str, out: &mut W) -> TACResult<()> {
+        self.interpret_all_to(&[source], out)
+    }
+
+    pub fn interpret_all(&mut self, sources: &[&str]) -> TACResult<()> {
+        self.interpret_all_to(sources, &mut io::stdout())
+    }
+
+    /// Compiles the sources one after the other into the same chunk and runs it, writing the
+    /// output of the program to `out`. Unless implicit halts are disabled, the program stops at
+    /// the end of the first source.
+    pub fn interpret_all_to<W: Write>(&mut self, sources: &[&str], out: &mut W) -> TACResult<()> {
+        self.load_all(sources)?;
+        self.run(out)
+    }
+
+    pub fn load(&mut self, source: &str) -> TACResult<()> {
+        self.load_all(&[source])
+    }
+
+    /// Compiles the sources like `interpret_all` and prepares to run the program from its start
+    /// without executing anything, so that it can be driven one instruction at a time with
+    /// `step`.
+    pub fn load_all(&mut self, sources: &[&str]) -> TACResult<()> {
+        self.interrupted.store(false, Ordering::SeqCst);
         self.chunk = Chunk::new();
+        self.chunk.keep_labels = self.disassembles();
         self.frames.clear();
         self.frames.push(Frame::default());
+        self.arrays.clear();
         self.ip = 0;
+        self.steps = 0;
+        self.paused = false;
+        self.result = None;
+
+        for source in sources {
+            Compiler::compile(source, &mut self.chunk, self.implicit_halt)?;
+        }
+
+        if self.optimize {
+            optimizer::optimize(&mut self.chunk);
+        }
+
+        self.check_types()?;
+        self.load_data(0);
+
+        if let Some(covered) = &mut self.coverage {
+            *covered = vec![false; self.chunk.code.len()];
+        }
+
+        if let Some(counts) = &mut self.profile {
+            counts.clear();
+        }
+
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        Ok(())
+    }
+
+    pub fn interpret_incremental(&mut self, source: &str) -> TACResult<()> {
+        self.interpret_incremental_to(source, &mut io::stdout())
+    }
+
+    /// Compiles the source after the code of the previous calls and runs only the new code,
+    /// keeping the variables of the main frame from one call to the next, as the REPL does with
+    /// each line. A source that does not compile leaves the machine untouched, and one that fails
+    /// at runtime forgets the variables it created. Peephole optimizations are not applied, as
+    /// they would move the code that already ran.
+    pub fn interpret_incremental_to<W: Write>(
+        &mut self,
+        source: &str,
+        out: &mut W,
+    ) -> TACResult<()> {
+        self.interrupted.store(false, Ordering::SeqCst);
+        // a halt inside a call leaves its frames behind
+        self.frames.truncate(1);
+        if self.frames.is_empty() {
+            self.frames.push(Frame::default());
+        }
+
+        let start = self.chunk.code.len();
+        let data_start = self.chunk.data.len();
+        let mark = self.chunk.mark();
+
+        self.chunk.keep_labels = self.disassembles();
+        let compiled = Compiler::compile(source, &mut self.chunk, true);
+        if let Err(err) = compiled.and_then(|()| self.check_types()) {
+            self.chunk.truncate(mark);
+            return Err(err);
+        }
+
+        let stack_len = self.stack.len();
+        let st = self.get_current_st().clone();
+        self.load_data(data_start);
+
+        if let Some(covered) = &mut self.coverage {
+            covered.resize(self.chunk.code.len(), false);
+        }
+
+        self.ip = start;
+        self.steps = 0;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let result = self.run(out);
+        if result.is_err() {
+            self.frames.truncate(1);
+            *self.get_current_st_mut() = st;
+            self.stack.truncate(stack_len);
+            self.arrays.retain(|base, _| *base < stack_len);
+        }
+
+        result
+    }
+
+    /// Index of the next instruction to execute.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /// Compiled program being executed.
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// Frame of the function being executed.
+    pub fn current_frame(&self) -> &Frame {
+        self.get_current_frame()
+    }
+
+    /// Lines whose instructions were all left unexecuted by the last program, when coverage is
+    /// being recorded.
+    pub fn uncovered_lines(&self) -> Vec<usize> {
+        let covered = match &self.coverage {
+            Some(covered) => covered,
+            None => return vec![],
+        };
+
+        let mut lines: Vec<(usize, bool)> = vec![];
+
+        for (idx, executed) in covered.iter().enumerate() {
+            let line = self.chunk.get_line(idx);
+
+            match lines.last_mut() {
+                Some((last, any_executed)) if *last == line => *any_executed |= executed,
+                _ => lines.push((line, *executed)),
+            }
+        }
+
+        lines
+            .into_iter()
+            .filter(|(_, executed)| !executed)
+            .map(|(line, _)| line)
+            .collect()
+    }
+
+    /// Prints the lines and instructions that were never executed by the last program, when
+    /// coverage is being recorded.
+    pub fn report_coverage(&self) {
+        let covered = match &self.coverage {
+            Some(covered) => covered,
+            None => return,
+        };
+
+        let executed = covered.iter().filter(|executed| **executed).count();
+        eprintln!(
+            "=== Coverage: {}/{} instructions executed ===",
+            executed,
+            covered.len()
+        );
+
+        let lines = self.uncovered_lines();
+        if !lines.is_empty() {
+            let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            eprintln!("Lines never executed: {}", lines.join(", "));
+        }
+
+        let dis = crate::disassembler::Disassembler::new(&self.chunk);
+        for (idx, instruction) in self.chunk.code.iter().enumerate() {
+            if !covered[idx] {
+                dis.instruction(idx, instruction);
+            }
+        }
+    }
+
+    /// Whether any instruction will be disassembled, by tracing or by the coverage and profile
+    /// reports, which show the labels of the program.
+    fn disassembles(&self) -> bool {
+        *crate::TRACE_EXECUTION.read().unwrap() || self.coverage.is_some() || self.profile.is_some()
+    }
+
+    /// Reports the type errors of the compiled program, if type checking is enabled.
+    fn check_types(&self) -> TACResult<()> {
+        if !self.typecheck {
+            return Ok(());
+        }
+
+        let errors = typecheck::check(&self.chunk);
+        for (line, message) in &errors {
+            eprintln!("[line {}] Type error: {}", line, message);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(TACError::CompileError)
+        }
+    }
+
+    /// Offset of each instruction executed by the last program and how many times it ran, the
+    /// most executed first, when profiling.
+    pub fn profile(&self) -> Vec<(usize, u64)> {
+        let mut counts: Vec<(usize, u64)> = match &self.profile {
+            Some(counts) => counts.iter().map(|(ip, count)| (*ip, *count)).collect(),
+            None => return vec![],
+        };
+
+        counts.sort_by(|(a_ip, a), (b_ip, b)| b.cmp(a).then(a_ip.cmp(b_ip)));
+        counts
+    }
 
-        Compiler::compile(source, &mut self.chunk)?;
+    /// Prints the instructions executed by the last program along with how many times each one
+    /// ran, the most executed first, when profiling.
+    pub fn report_profile(&self) {
+        if self.profile.is_none() {
+            return;
+        }
+
+        let counts = self.profile();
+        let total: u64 = counts.iter().map(|(_, count)| count).sum();
+        eprintln!("=== Profile: {} instructions executed ===", total);
+
+        let dis = crate::disassembler::Disassembler::new(&self.chunk);
+        for (ip, count) in counts {
+            eprint!("{:>10} ", count);
+            dis.instruction(ip, &self.chunk.code[ip]);
+        }
+    }
+
+    /// Prints how long the last program took and how many instructions it executed, when it is
+    /// being timed.
+    pub fn report_time(&self) {
+        if let Some(elapsed) = self.elapsed {
+            eprintln!(
+                "Executed {} instructions in {:.3} ms",
+                self.steps,
+                elapsed.as_secs_f64() * 1000.0
+            );
+        }
+    }
 
-        self.run()
+    /// Places the elements of each data array from the one at index `from` onwards in a region
+    /// of consecutive stack slots. The region is followed by a variable for each array, holding
+    /// the address of its first element, or the value itself for lone values.
+    fn load_data(&mut self, from: usize) {
+        let mut bases = Vec::with_capacity(self.chunk.data.len() - from);
+        for array in self.chunk.data[from..].iter().filter(|array| !array.scalar) {
+            bases.push(self.stack.len());
+            self.arrays.insert(self.stack.len(), array.values.len());
+            self.stack.extend(array.values.iter().copied());
+        }
+
+        let mut bases = bases.into_iter();
+        for array in &self.chunk.data[from..] {
+            let value = match array.scalar {
+                true => array.values[0],
+                false => Value::Addr(bases.next().unwrap()),
+            };
+
+            let frame = self.frames.last_mut().unwrap();
+            frame.st.insert(array.name, self.stack.len());
+            self.stack.push(value);
+        }
     }
 
     fn get_current_frame(&self) -> &Frame {
@@ -99,66 +591,205 @@ impl VirtualMachine {
         &mut self.get_current_frame_mut().st
     }
 
-    fn run(&mut self) -> TACResult<()> {
+    fn run<W: Write>(&mut self, out: &mut W) -> TACResult<()> {
+        let start = Instant::now();
+
+        let result = loop {
+            match self.step_to(out) {
+                Ok(StepResult::Halted) => break Ok(()),
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        if let Some(elapsed) = &mut self.elapsed {
+            *elapsed = start.elapsed();
+        }
+
+        result
+    }
+
+    /// Executes the program loaded with `load` until it finishes or reaches a breakpoint.
+    pub fn resume(&mut self) -> TACResult<StepResult> {
+        self.resume_to(&mut io::stdout())
+    }
+
+    /// Same as `resume`, but the output of the program is written to `out` instead of stdout.
+    pub fn resume_to<W: Write>(&mut self, out: &mut W) -> TACResult<StepResult> {
         loop {
-            let instruction = match self.chunk.code.get(self.ip) {
-                Some(i) => *i,
-                None => {
-                    return Err(self.report_rte(
-                        "Instruction pointer reached end of code without a finishing statement"
-                            .into(),
-                    ))
-                }
-            };
+            match self.step_to(out)? {
+                StepResult::Continue => {}
+                result => return Ok(result),
+            }
+        }
+    }
 
-            {
-                let trace_execution = crate::TRACE_EXECUTION.read().unwrap();
-                if *trace_execution {
-                    let dis = crate::disassembler::Disassembler::new(&self.chunk);
-                    dis.instruction(self.ip, &instruction);
-                }
+    /// Whether the next instruction starts a line with a breakpoint, either because it follows
+    /// an instruction of another line or because it is the first one executed.
+    fn at_breakpoint(&self) -> bool {
+        if self.breakpoints.is_empty() || self.ip >= self.chunk.code.len() {
+            return false;
+        }
+
+        let line = self.chunk.get_line(self.ip);
+        let entered = self.steps == 0 || self.chunk.get_line(self.current_ip) != line;
+
+        entered && self.breakpoints.contains(&line)
+    }
+
+    /// Executes the next instruction of the program loaded with `load`, returning whether the
+    /// program finished.
+    pub fn step(&mut self) -> TACResult<StepResult> {
+        self.step_to(&mut io::stdout())
+    }
+
+    /// Same as `step`, but the output of the instruction is written to `out` instead of stdout.
+    pub fn step_to<W: Write>(&mut self, out: &mut W) -> TACResult<StepResult> {
+        if !self.paused && self.at_breakpoint() {
+            self.paused = true;
+            return Ok(StepResult::Breakpoint);
+        }
+        self.paused = false;
+
+        self.current_ip = self.ip;
+
+        let instruction = match self.chunk.code.get(self.ip) {
+            Some(i) => *i,
+            None => {
+                return Err(self.report_rte(
+                    "Instruction pointer reached end of code without a finishing statement".into(),
+                ))
+            }
+        };
+
+        {
+            let trace_execution = crate::TRACE_EXECUTION.read().unwrap();
+            if *trace_execution {
+                let dis = crate::disassembler::Disassembler::new(&self.chunk);
+                dis.instruction(self.ip, &instruction);
             }
+        }
+
+        if self.interrupted.load(Ordering::Relaxed) {
+            self.interrupted.store(false, Ordering::Relaxed);
+            return Err(self.report_rte("Execution interrupted".into()));
+        }
+
+        if matches!(self.step_limit, Some(limit) if self.steps >= limit) {
+            return Err(self.report_rte(format!(
+                "Step limit of {} instructions exceeded",
+                self.steps
+            )));
+        }
+        self.steps += 1;
+
+        if let Some(deadline) = self.deadline {
+            // u64::is_multiple_of is too recent for the toolchains the crate builds with
+            #[allow(clippy::manual_is_multiple_of)]
+            let check = self.steps % self.timeout_interval == 0;
+            if check && Instant::now() >= deadline {
+                return Err(self.report_rte(format!(
+                    "Timeout of {} ms exceeded",
+                    self.timeout.unwrap_or_default().as_millis()
+                )));
+            }
+        }
+
+        if let Some(covered) = &mut self.coverage {
+            covered[self.ip] = true;
+        }
+
+        if let Some(counts) = &mut self.profile {
+            *counts.entry(self.ip).or_insert(0) += 1;
+        }
+
+        self.ip += 1;
 
-            self.ip += 1;
+        self.reserve_stack(instruction.stack_effect().1)?;
 
-            match instruction {
-                Instruction::Halt => return Ok(()),
-                Instruction::Return => {
-                    // Calls return method, if it returns true, it means
-                    // we have returned from the last frame and we should
-                    // terminate the program.
-                    if self.r#return() {
-                        return Ok(());
-                    }
+        match instruction {
+            Instruction::Halt => return Ok(StepResult::Halted),
+            Instruction::Return => {
+                // Calls return method, if it returns true, it means
+                // we have returned from the last frame and we should
+                // terminate the program.
+                if self.r#return(None) {
+                    return Ok(StepResult::Halted);
                 }
-                Instruction::Negate => self.negate()?,
-                Instruction::Not => self.not()?,
-                Instruction::Constant(addr) => self.constant(addr)?,
-                Instruction::GetVar(name_addr) => self.get_var(name_addr)?,
-                Instruction::GetOrCreateVar(name_addr) => self.get_or_create_var(name_addr),
-                Instruction::True => self.stack.push(Value::Bool(true)),
-                Instruction::False => self.stack.push(Value::Bool(false)),
-                Instruction::Add => binary_op!(self, +),
-                Instruction::Subtract => binary_op!(self, -),
-                Instruction::Multiply => binary_op!(self, *),
-                Instruction::Divide => binary_op!(self, /),
-                Instruction::Modulo => binary_op!(self, %),
-                Instruction::ShiftLeft => binary_op!(self, <<),
-                Instruction::ShiftRight => binary_op!(self, >>),
-                Instruction::Equal => binary_op_f!(self, eq),
-                Instruction::Greater => binary_op_f!(self, gt),
-                Instruction::Less => binary_op_f!(self, lt),
-                Instruction::Print(nl) => self.print(nl)?,
-                Instruction::Goto(ip) => self.ip = ip as usize,
-                Instruction::JumpIf(ip) => self.jump_if(ip)?,
-                Instruction::Assign => self.assign()?,
-                Instruction::Call(ip) => self.call(ip)?,
-            }
-        }
-    }
-
-    /// Pops the current frame, return whether it was the last frame available.
-    fn r#return(&mut self) -> bool {
+            }
+            Instruction::ReturnValue => {
+                let value = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| self.report_rte("No value in the stack to return".into()))?;
+                if self.r#return(Some(value)) {
+                    return Ok(StepResult::Halted);
+                }
+            }
+            Instruction::PushResult => self.push_result()?,
+            Instruction::Negate => self.negate()?,
+            Instruction::Dereference => self.dereference()?,
+            Instruction::Reference(name_addr) => self.reference(name_addr)?,
+            Instruction::Not => self.not()?,
+            Instruction::BitNot => self.bitnot()?,
+            Instruction::Constant(addr) => self.constant(addr)?,
+            Instruction::GetVar(name_addr) => self.get_var(name_addr)?,
+            Instruction::GetOrCreateVar(name_addr) => self.get_or_create_var(name_addr)?,
+            Instruction::Pop => self.pop()?,
+            Instruction::Dup => self.dup()?,
+            Instruction::Swap => self.swap()?,
+            Instruction::True => self.stack.push(Value::Bool(true)),
+            Instruction::False => self.stack.push(Value::Bool(false)),
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Power
+                if self.arithmetic != ArithmeticMode::Wrap =>
+            {
+                self.arithmetic_op(instruction)?
+            }
+            Instruction::Add => binary_op!(self, +),
+            Instruction::Subtract => binary_op!(self, -),
+            Instruction::Multiply => binary_op!(self, *),
+            Instruction::Divide => binary_op!(self, /),
+            Instruction::Modulo => binary_op!(self, %),
+            Instruction::Power => binary_op_f!(self, pow),
+            Instruction::ShiftLeft => binary_op!(self, <<),
+            Instruction::ShiftRight => binary_op!(self, >>),
+            Instruction::BitAnd => binary_op!(self, &),
+            Instruction::BitOr => binary_op!(self, |),
+            Instruction::BitXor => binary_op!(self, ^),
+            Instruction::Equal => binary_op_f!(self, eq),
+            Instruction::Greater => binary_op_f!(self, gt),
+            Instruction::Less => binary_op_f!(self, lt),
+            Instruction::Print(nl) => self.print(out, nl)?,
+            Instruction::PrintWidth => self.print_width(out)?,
+            Instruction::Inspect => self.inspect(out)?,
+            Instruction::Goto(ip) => self.ip = ip as usize,
+            Instruction::JumpIf(ip) => self.jump_if(ip)?,
+            Instruction::Assign => self.assign()?,
+            Instruction::Call(ip) => self.call(ip)?,
+            Instruction::TailCall(ip) => self.tail_call(ip)?,
+            Instruction::Intrinsic(intrinsic) => self.intrinsic(intrinsic)?,
+            Instruction::Clear(name_addr) => self.clear(name_addr)?,
+            Instruction::Checksum(name_addr) => self.checksum(name_addr)?,
+            Instruction::Index(name_addr) => self.index(name_addr)?,
+            Instruction::IndexAddr(name_addr) => self.index_addr(name_addr)?,
+            Instruction::Scan(tag) => self.scan(out, tag)?,
+            Instruction::Cast(tag) => self.cast(tag)?,
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    /// Pops the current frame, return whether it was the last frame available. The program's own
+    /// frame is never popped, so that its variables outlive a `return` from it as they do a
+    /// `halt`, which the REPL relies on. The value given to `return`, if any, is kept for
+    /// `PushResult`.
+    fn r#return(&mut self, value: Option<Value>) -> bool {
+        self.result = value;
+
         let ra = self.get_current_frame().ra;
         let rsp = self.get_current_frame().rsp;
 
@@ -168,12 +799,70 @@ impl VirtualMachine {
             self.ip = ip;
             false
         } else {
-            self.frames.pop();
+            if self.frames.len() > 1 {
+                self.frames.pop();
+            }
             true
         }
     }
 
+    /// Calls the subroutine at `ip`. The stack holds the parameters pushed by `param` followed by
+    /// their count, all of which are popped. The new frame starts at the current top of the stack
+    /// with the `params_len` variable, followed by the parameters in reverse order of how they
+    /// were pushed, `params` naming the first of them, i.e. the last one pushed.
+    ///
+    /// A call made by `return call` is a tail call instead, see `tail_call`.
+    ///
+    /// Returning truncates the stack back to where the frame started, so nothing computed by the
+    /// subroutine is left in the caller's stack. The value given to `return` is kept aside
+    /// instead, and the caller pushes it with `PushResult` once it is ready to use it, e.g. after
+    /// pushing the variable it is assigned to.
     fn call(&mut self, ip: u16) -> TACResult<()> {
+        let parameters = self.pop_parameters()?;
+
+        let frame = Frame {
+            ra: Some(self.ip),
+            rsp: self.stack.len(),
+            ..Default::default()
+        };
+
+        self.enter(ip, frame, parameters)
+    }
+
+    /// Calls the subroutine at `ip` from a `return`, so that it returns straight to the caller of
+    /// the current subroutine. The current frame is replaced instead of growing the call stack,
+    /// its variables being dropped unless one of the parameters points to them. The program's
+    /// own frame is kept, as returning from the callee ends the program anyway, while the frames
+    /// of later tail calls from it are replaced like any other.
+    fn tail_call(&mut self, ip: u16) -> TACResult<()> {
+        let parameters = self.pop_parameters()?;
+
+        let frame = if self.frames.len() == 1 {
+            Frame {
+                rsp: self.stack.len(),
+                ..Default::default()
+            }
+        } else {
+            let current = self.frames.pop().unwrap();
+            let referenced = parameters
+                .iter()
+                .any(|param| matches!(param, Value::Addr(addr) if *addr >= current.rsp));
+            if !referenced {
+                self.stack.truncate(current.rsp);
+            }
+
+            Frame {
+                ra: current.ra,
+                rsp: current.rsp,
+                ..Default::default()
+            }
+        };
+
+        self.enter(ip, frame, parameters)
+    }
+
+    /// Pops the parameter count and then that many parameters, in the order they are popped.
+    fn pop_parameters(&mut self) -> TACResult<Vec<Value>> {
         let param_count = self.stack.pop().ok_or_else(|| {
             self.report_rte(
                 "No value in the stack to define how many parameters to call function".into(),
@@ -182,7 +871,13 @@ impl VirtualMachine {
 
         let mut parameters = vec![];
 
-        if let Value::U64(count) = param_count {
+        let count = match param_count {
+            Value::U64(count) => Some(count),
+            Value::I64(count) => u64::try_from(count).ok(),
+            _ => None,
+        };
+
+        if let Some(count) = count {
             for i in 0..count {
                 parameters.push(self.stack.pop().ok_or_else(|| {
                     self.report_rte(format!(
@@ -193,11 +888,17 @@ impl VirtualMachine {
             }
         } else {
             return Err(self.report_rte(format!(
-                "Parameter count must be defined by a variable of type u64 but found type {}",
+                "Parameter count must be a non-negative integer but found '{}' of type {}",
+                self.chunk.display_value(param_count, false),
                 param_count.type_info()
             )));
         }
 
+        Ok(parameters)
+    }
+
+    /// Pushes the frame of a subroutine starting at `ip` along with its parameters.
+    fn enter(&mut self, ip: u16, frame: Frame, parameters: Vec<Value>) -> TACResult<()> {
         // get string id of "params" name
         let params_name_addr = self
             .chunk
@@ -209,12 +910,16 @@ impl VirtualMachine {
             .add_name("params_len")
             .map_err(|_| self.report_rte("The program uses too many variables (65535+)".into()))?;
 
-        // push new empty frame
-        let frame = Frame {
-            ra: Some(self.ip),
-            rsp: self.stack.len(),
-            ..Default::default()
-        };
+        self.reserve_stack(1 + parameters.len())?;
+
+        // the first frame belongs to the program itself
+        if self.frames.len() > self.depth_limit {
+            return Err(self.report_rte(format!(
+                "Maximum recursion depth of {} calls exceeded",
+                self.depth_limit
+            )));
+        }
+
         self.frames.push(frame);
 
         // insert "params_len" variable in symbol table, address 0: beginning of the stack
@@ -243,24 +948,167 @@ impl VirtualMachine {
         Ok(())
     }
 
-    fn assign(&mut self) -> TACResult<()> {
-        let value = self
-            .stack
-            .pop()
-            .ok_or_else(|| self.report_rte("No value in the stack to assign".into()))?;
+    fn intrinsic(&mut self, intrinsic: Intrinsic) -> TACResult<()> {
+        let arity = intrinsic.arity();
 
-        let addr = self
-            .stack
-            .pop()
-            .ok_or_else(|| self.report_rte("No address in the stack to assign to".into()))?;
+        if self.stack.len() < arity {
+            return Err(self.report_rte(format!(
+                "Can not call intrinsic '{}' because there are not enough values in the stack",
+                intrinsic.name()
+            )));
+        }
 
-        if let Value::Addr(addr) = addr {
-            let map = match self.stack.get_mut(addr) {
-                Some(val) => val,
-                None => {
-                    return Err(
-                        self.report_rte("Assignment target points to invalid stack address".into())
-                    )
+        let args = self.stack.split_off(self.stack.len() - arity);
+
+        let result = match intrinsic {
+            Intrinsic::ToUpper => args[0].to_uppercase(),
+            Intrinsic::ToLower => args[0].to_lowercase(),
+            Intrinsic::ArgCount => Ok(Value::U64(self.args.len() as u64)),
+            Intrinsic::Arg => self.arg(args[0]),
+            Intrinsic::Pack => Value::pack(args[0], args[1]),
+            Intrinsic::UnpackHi => args[0].unpack_hi(),
+            Intrinsic::UnpackLo => args[0].unpack_lo(),
+            Intrinsic::IsNumeric => Ok(Value::Bool(args[0].is_numeric())),
+            Intrinsic::IsInt => Ok(Value::Bool(args[0].is_int())),
+            Intrinsic::IsFloat => Ok(Value::Bool(args[0].is_float())),
+            Intrinsic::Clamp => args[0].clamp(args[1], args[2]),
+            Intrinsic::StepsLeft => Ok(Value::U64(self.steps_left())),
+            Intrinsic::StackLeft => Ok(Value::U64(
+                self.stack_limit.saturating_sub(self.stack.len()) as u64,
+            )),
+        };
+
+        match result {
+            Ok(val) => {
+                self.stack.push(val);
+                Ok(())
+            }
+            Err(msg) => Err(self.report_rte(msg)),
+        }
+    }
+
+    fn arg(&self, index: Value) -> Result<Value, String> {
+        let idx = match index {
+            Value::U64(idx) => usize::try_from(idx).ok(),
+            Value::I64(idx) => usize::try_from(idx).ok(),
+            val => {
+                return Err(format!(
+                    "Argument index must be an integer but found type {}",
+                    val.type_info()
+                ))
+            }
+        };
+
+        match idx.and_then(|idx| self.args.get(idx)) {
+            Some(arg) => Value::from_arg(arg),
+            None => Err(format!(
+                "Argument index {} is out of range, the program received {} arguments",
+                index,
+                self.args.len()
+            )),
+        }
+    }
+
+    fn clear(&mut self, name_addr: u16) -> TACResult<()> {
+        let (base, len) = self.get_array(name_addr)?;
+
+        for value in &mut self.stack[base..base + len] {
+            *value = value.zero();
+        }
+
+        Ok(())
+    }
+
+    fn checksum(&mut self, name_addr: u16) -> TACResult<()> {
+        let (base, len) = self.get_array(name_addr)?;
+
+        let checksum = Value::checksum(&self.stack[base..base + len]);
+        self.stack.push(Value::U64(checksum));
+
+        Ok(())
+    }
+
+    fn index(&mut self, name_addr: u16) -> TACResult<()> {
+        let addr = self.element_addr(name_addr)?;
+        let value = self.stack[addr];
+        self.stack.push(value);
+
+        Ok(())
+    }
+
+    fn index_addr(&mut self, name_addr: u16) -> TACResult<()> {
+        let addr = self.element_addr(name_addr)?;
+        self.stack.push(Value::Addr(addr));
+
+        Ok(())
+    }
+
+    /// Pops an index and returns the stack address of that element of the array a variable points
+    /// to, elements being stored in consecutive slots starting at the address held by the variable.
+    fn element_addr(&mut self, name_addr: u16) -> TACResult<usize> {
+        let index = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No index in the stack to access the array".into()))?;
+        let (base, len) = self.get_array(name_addr)?;
+
+        let offset = match index {
+            Value::U64(idx) => usize::try_from(idx).ok(),
+            Value::I64(idx) => usize::try_from(idx).ok(),
+            val => {
+                return Err(self.report_rte(format!(
+                    "Array index must be an integer but found type {}",
+                    val.type_info()
+                )))
+            }
+        };
+
+        match offset {
+            Some(offset) if offset < len => Ok(base + offset),
+            _ => Err(self.report_rte(format!(
+                "Index {} is out of bounds for array {} of length {}",
+                index,
+                self.chunk.get_name(name_addr),
+                len
+            ))),
+        }
+    }
+
+    /// Address of the first element and length of the array a variable points to.
+    fn get_array(&self, name_addr: u16) -> TACResult<(usize, usize)> {
+        let name = self.chunk.get_name(name_addr);
+
+        let addr = match self.get_current_st().get(&name_addr) {
+            Some(addr) => *addr,
+            None => return Err(self.report_rte(format!("Variable {} is undefined", name))),
+        };
+
+        match self.stack.get(addr) {
+            Some(Value::Addr(base)) if self.arrays.contains_key(base) => {
+                Ok((*base, self.arrays[base]))
+            }
+            _ => Err(self.report_rte(format!("Variable {} is not an array", name))),
+        }
+    }
+
+    fn assign(&mut self) -> TACResult<()> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No value in the stack to assign".into()))?;
+
+        let addr = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No address in the stack to assign to".into()))?;
+
+        if let Value::Addr(addr) = addr {
+            let map = match self.stack.get_mut(addr) {
+                Some(val) => val,
+                None => {
+                    return Err(
+                        self.report_rte("Assignment target points to invalid stack address".into())
+                    )
                 }
             };
 
@@ -271,7 +1119,7 @@ impl VirtualMachine {
         }
     }
 
-    fn print(&mut self, nl: bool) -> TACResult<()> {
+    fn print<W: Write>(&mut self, out: &mut W, nl: bool) -> TACResult<()> {
         let value = self
             .stack
             .pop()
@@ -282,7 +1130,87 @@ impl VirtualMachine {
             false => "",
         };
 
-        print!("{}{}", value, suffix);
+        let text = self.chunk.display_value(value, false);
+        write!(out, "{}{}", text, suffix).map_err(|err| self.write_error(err))
+    }
+
+    fn print_width<W: Write>(&mut self, out: &mut W) -> TACResult<()> {
+        let (value, width) = match (self.stack.pop(), self.stack.pop()) {
+            (Some(width), Some(value)) => (value, width),
+            _ => return Err(self.report_rte("Not enough values in the stack to printw".into())),
+        };
+
+        let width = match width {
+            Value::U64(w) => usize::try_from(w).ok(),
+            Value::I64(w) => usize::try_from(w).ok(),
+            _ => None,
+        };
+
+        match width {
+            Some(width) if width <= MAX_PRINT_WIDTH => write!(
+                out,
+                "{:>width$}",
+                self.chunk.display_value(value, false),
+                width = width
+            )
+            .map_err(|err| self.write_error(err)),
+            _ => Err(self.report_rte(format!(
+                "Width of printw must be an integer between 0 and {}",
+                MAX_PRINT_WIDTH
+            ))),
+        }
+    }
+
+    fn inspect<W: Write>(&mut self, out: &mut W) -> TACResult<()> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No value in the stack to inspect".into()))?;
+
+        let text = self.chunk.display_value(value, true);
+        writeln!(out, "{}", text).map_err(|err| self.write_error(err))
+    }
+
+    fn steps_left(&self) -> u64 {
+        match self.step_limit {
+            Some(limit) => limit.saturating_sub(self.steps),
+            None => UNLIMITED,
+        }
+    }
+
+    fn write_error(&self, err: io::Error) -> TACError {
+        self.report_rte(format!("Could not write output: {}", err))
+    }
+
+    fn scan<W: Write>(&mut self, out: &mut W, tag: TypeTag) -> TACResult<()> {
+        // prompts printed without a newline must be visible before the program blocks on input
+        out.flush().map_err(|err| self.write_error(err))?;
+
+        let mut line = String::new();
+        let read = match &mut self.input {
+            Some(input) => input.read_line(&mut line),
+            None => io::stdin().read_line(&mut line),
+        };
+
+        match read {
+            Ok(0) => Err(self.report_rte("No input left to scan".into())),
+            Ok(_) => {
+                let value = Value::parse(&line, tag).map_err(|msg| self.report_rte(msg))?;
+                self.stack.push(value);
+                Ok(())
+            }
+            Err(err) => Err(self.report_rte(format!("Could not read input: {}", err))),
+        }
+    }
+
+    fn cast(&mut self, tag: TypeTag) -> TACResult<()> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No value in the stack to cast".into()))?;
+
+        let value = value.cast(tag).map_err(|msg| self.report_rte(msg))?;
+        self.stack.push(value);
         Ok(())
     }
 
@@ -305,6 +1233,27 @@ impl VirtualMachine {
         }
     }
 
+    fn arithmetic_op(&mut self, instruction: Instruction) -> TACResult<()> {
+        if self.stack.len() < 2 {
+            return Err(self.report_rte(format!(
+                "Can not apply operator '{:?}' because there are not enough values in the stack",
+                instruction
+            )));
+        }
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        match instruction.apply_arithmetic(a, b, self.arithmetic) {
+            Some(Ok(value)) => {
+                self.stack.push(value);
+                Ok(())
+            }
+            Some(Err(msg)) => Err(self.report_rte(msg)),
+            None => panic!("Invalid instruction in arithmetic_op()"),
+        }
+    }
+
     fn negate(&mut self) -> TACResult<()> {
         match self.stack.last_mut().map(Value::arithmetic_negate) {
             Some(Ok(_)) => Ok(()),
@@ -327,6 +1276,95 @@ impl VirtualMachine {
         }
     }
 
+    fn bitnot(&mut self) -> TACResult<()> {
+        match self.stack.last_mut().map(Value::bitwise_negate) {
+            Some(Ok(_)) => Ok(()),
+            Some(Err(msg)) => Err(self.report_rte(msg)),
+            None => Err(self.report_rte(
+                "Can not apply unary operator '~' because there is not a value in the stack"
+                    .to_string(),
+            )),
+        }
+    }
+
+    fn reference(&mut self, name_addr: u16) -> TACResult<()> {
+        match self.get_current_st().get(&name_addr) {
+            Some(addr) => {
+                self.stack.push(Value::Addr(*addr));
+                Ok(())
+            }
+            None => Err(self.report_rte(format!(
+                "Variable {} is undefined",
+                self.chunk.get_name(name_addr)
+            ))),
+        }
+    }
+
+    fn dereference(&mut self) -> TACResult<()> {
+        let addr = self
+            .stack
+            .pop()
+            .ok_or_else(|| self.report_rte("No address in the stack to dereference".into()))?;
+
+        let addr = match addr {
+            Value::Addr(addr) => addr,
+            v => {
+                return Err(self.report_rte(format!(
+                    "Can not dereference a value of type '{}', 'addr' required",
+                    v.type_info()
+                )))
+            }
+        };
+
+        match self.stack.get(addr).copied() {
+            Some(value) => {
+                self.stack.push(value);
+                Ok(())
+            }
+            None => {
+                Err(self.report_rte("Dereferenced pointer points to invalid stack address".into()))
+            }
+        }
+    }
+
+    fn pop(&mut self) -> TACResult<()> {
+        match self.stack.pop() {
+            Some(_) => Ok(()),
+            None => Err(self.report_rte("No value in the stack to discard".into())),
+        }
+    }
+
+    fn push_result(&mut self) -> TACResult<()> {
+        match self.result {
+            Some(value) => {
+                self.stack.push(value);
+                Ok(())
+            }
+            None => Err(self.report_rte("The last subroutine called returned no value".into())),
+        }
+    }
+
+    fn dup(&mut self) -> TACResult<()> {
+        match self.stack.last() {
+            Some(value) => {
+                let value = *value;
+                self.stack.push(value);
+                Ok(())
+            }
+            None => Err(self.report_rte("No value in the stack to duplicate".into())),
+        }
+    }
+
+    fn swap(&mut self) -> TACResult<()> {
+        let len = self.stack.len();
+        if len < 2 {
+            return Err(self.report_rte("Not enough values in the stack to swap".into()));
+        }
+
+        self.stack.swap(len - 1, len - 2);
+        Ok(())
+    }
+
     fn constant(&mut self, addr: u16) -> TACResult<()> {
         let value = self.read_constant(addr)?;
         self.stack.push(value);
@@ -337,10 +1375,17 @@ impl VirtualMachine {
         let addr = match self.get_current_st().get(&name_addr) {
             Some(addr) => *addr,
             None => {
-                return Err(self.report_rte(format!(
-                    "Variable {} is undefined",
-                    self.chunk.get_name(name_addr)
-                )))
+                let name = self.chunk.get_name(name_addr);
+                let defined = self
+                    .get_current_st()
+                    .keys()
+                    .map(|name_addr| self.chunk.get_name(*name_addr));
+
+                return Err(self.report_rte(did_you_mean(
+                    format!("Variable {} is undefined", name),
+                    name,
+                    defined,
+                )));
             }
         };
 
@@ -356,7 +1401,10 @@ impl VirtualMachine {
         }
     }
 
-    fn get_or_create_var(&mut self, name_addr: u16) {
+    fn get_or_create_var(&mut self, name_addr: u16) -> TACResult<()> {
+        // room for the variable itself and its address
+        self.reserve_stack(2)?;
+
         let cur_sp = self.stack.len();
         let addr = match self.get_current_st_mut().entry(name_addr) {
             Entry::Occupied(entry) => *entry.get(),
@@ -368,6 +1416,20 @@ impl VirtualMachine {
         };
 
         self.stack.push(Value::Addr(addr));
+
+        Ok(())
+    }
+
+    /// Fails when pushing `values` more values would exceed the stack limit.
+    fn reserve_stack(&self, values: usize) -> TACResult<()> {
+        if self.stack.len() + values > self.stack_limit {
+            return Err(self.report_rte(format!(
+                "Stack overflow, the stack is limited to {} values",
+                self.stack_limit
+            )));
+        }
+
+        Ok(())
     }
 
     fn read_constant(&mut self, addr: u16) -> TACResult<Value> {
@@ -375,10 +1437,1067 @@ impl VirtualMachine {
     }
 
     fn report_rte(&self, message: String) -> TACError {
-        let line = self.chunk.get_line(self.ip);
+        let line = self.chunk.get_line(self.current_ip);
         eprintln!("{}", message);
         eprintln!("[line {}] in script", line);
 
         TACError::RuntimeError
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        io::{self, Cursor, Write},
+        rc::Rc,
+        sync::atomic::Ordering,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        chunk::Instruction,
+        error::{TACError, TACResult},
+        value::{ArithmeticMode, Value},
+        vm::{Frame, StepResult, VirtualMachine, DEFAULT_DEPTH_LIMIT},
+    };
+
+    /// Runs instructions that the compiler does not emit on their own, returning the stack left
+    /// behind. `Constant(n)` pushes the `n`th of the given constants.
+    fn run_code(constants: &[Value], code: &[Instruction]) -> (TACResult<()>, Vec<Value>) {
+        let mut vm = VirtualMachine::new();
+        for constant in constants {
+            vm.chunk.add_constant(*constant).unwrap();
+        }
+        for instruction in code {
+            vm.chunk.write(*instruction, 1);
+        }
+        vm.frames.push(Frame::default());
+
+        let result = vm.run(&mut vec![]);
+        (result, vm.stack)
+    }
+
+    #[test]
+    fn swap_exchanges_the_top_two_values() {
+        let constants = [Value::I64(1), Value::I64(2), Value::I64(3)];
+        let code = [
+            Instruction::Constant(0),
+            Instruction::Constant(1),
+            Instruction::Constant(2),
+            Instruction::Swap,
+            Instruction::Halt,
+        ];
+        let (result, stack) = run_code(&constants, &code);
+
+        assert!(result.is_ok());
+        assert_eq!(format!("{:?}", stack), "[I64(1), I64(3), I64(2)]");
+
+        let code = [
+            Instruction::Constant(0),
+            Instruction::Swap,
+            Instruction::Halt,
+        ];
+        let (result, _) = run_code(&constants, &code);
+        assert_eq!(result, Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn dup_pushes_a_copy_of_the_top_value() {
+        let code = [
+            Instruction::Constant(0),
+            Instruction::Dup,
+            Instruction::Halt,
+        ];
+        let (result, stack) = run_code(&[Value::I64(7)], &code);
+
+        assert!(result.is_ok());
+        assert_eq!(format!("{:?}", stack), "[I64(7), I64(7)]");
+
+        let (result, _) = run_code(&[], &[Instruction::Dup, Instruction::Halt]);
+        assert_eq!(result, Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn always_false_branch_is_uncovered() {
+        let mut vm = VirtualMachine::new().with_coverage(true);
+        let source = "if false goto never\nhalt\nnever:\nprint 1\n";
+
+        assert!(vm.interpret(source).is_ok());
+
+        let uncovered = vm.uncovered_lines();
+        assert!(uncovered.contains(&4));
+        assert!(!uncovered.contains(&1));
+        assert!(!uncovered.contains(&2));
+    }
+
+    #[test]
+    fn loop_body_is_the_most_executed() {
+        let mut vm = VirtualMachine::new().with_profile(true);
+        let source = "i = 0\ntop:\ni = i + 1\nif i < 10 goto top\n";
+
+        assert!(vm.interpret(source).is_ok());
+
+        let profile = vm.profile();
+        assert_eq!(profile.len(), vm.chunk.code.len());
+        assert_eq!(profile[0], (3, 10));
+        assert_eq!(profile.last(), Some(&(vm.chunk.code.len() - 1, 1)));
+        assert_eq!(
+            profile.iter().map(|(_, count)| count).sum::<u64>(),
+            vm.steps
+        );
+
+        let mut vm = VirtualMachine::new();
+        assert!(vm.interpret(source).is_ok());
+        assert!(vm.profile().is_empty());
+    }
+
+    #[test]
+    fn sources_without_implicit_halt_run_as_one() {
+        let mut vm = VirtualMachine::new().with_implicit_halt(false);
+
+        assert!(vm.interpret_all(&["x = 40\n", "y = x + 2\nhalt\n"]).is_ok());
+        assert!(matches!(vm.stack[1], Value::I64(42)));
+
+        assert_eq!(
+            vm.interpret_all(&["x = 40\n", "y = x + 2\n"]),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn implicit_halt_stops_after_first_source() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret_all(&["x = 40\n", "y = x + 2\n"]).is_ok());
+        assert_eq!(vm.stack.len(), 1);
+    }
+
+    #[test]
+    fn shift_expressions_compile_and_run() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("a = 3\nb = a << 4\nc = b >> 2\n").is_ok());
+        assert!(matches!(vm.stack[1], Value::I64(48)));
+        assert!(matches!(vm.stack[2], Value::I64(12)));
+
+        assert_eq!(
+            vm.interpret("a = 1.0\nb = a >> 1\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn bitwise_and_expression() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm
+            .interpret("a = 0xF0 & 0x0F\nb = a == 0\nc = 0xFF & 0x3C\n")
+            .is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(0)));
+        assert!(matches!(vm.stack[1], Value::Bool(true)));
+        assert!(matches!(vm.stack[2], Value::I64(0x3C)));
+    }
+
+    #[test]
+    fn bitwise_or_and_xor_expressions() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("a = 0xF0 | 0x0F\nb = a ^ 0x0F\n").is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(0xFF)));
+        assert!(matches!(vm.stack[1], Value::I64(0xF0)));
+
+        assert_eq!(
+            vm.interpret("a = 1.5\nb = a | 1.0\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn steps_left_decreases_under_a_step_limit() {
+        let mut vm = VirtualMachine::new().with_step_limit(100);
+
+        assert!(vm.interpret("a = steps_left\nb = steps_left\n").is_ok());
+        match (vm.stack[0], vm.stack[1]) {
+            (Value::U64(a), Value::U64(b)) => assert!(a < 100 && b < a),
+            _ => panic!("steps_left must return u64 values"),
+        }
+
+        let mut vm = VirtualMachine::new().with_stack_limit(100);
+        assert!(vm.interpret("a = steps_left\nb = stack_left\n").is_ok());
+        assert!(matches!(vm.stack[0], Value::U64(u64::MAX)));
+        // a and b are in the stack, b's address is below the intrinsic's result
+        assert!(matches!(vm.stack[1], Value::U64(97)));
+    }
+
+    #[test]
+    fn returned_values_are_pushed_for_the_caller() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "\
+param 7
+y = call square 1
+param 3
+call square 1
+param 4
+println call square 1
+println y
+halt
+square:
+x = params
+return x * x
+";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "16\n49\n");
+        // only y is left, the result of the call statement was never pushed
+        assert_eq!(vm.stack.len(), 1);
+
+        assert_eq!(
+            VirtualMachine::new().interpret("y = call f\nhalt\nf:\nreturn\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn tail_calls_reuse_the_current_frame() {
+        let countdown = |main: &str, tail: &str| {
+            format!(
+                "param 1000\n{}\ncountdown:\nn = params\nif n == 0 goto done\nm = n - 1\nparam m\n{}\ndone:\nreturn 42\n",
+                main, tail
+            )
+        };
+        let printed = "r = call countdown 1\nprintln r\nhalt";
+
+        let mut vm = VirtualMachine::new().with_depth_limit(100);
+        let mut out = vec![];
+        let source = countdown(printed, "return call countdown 1");
+        assert!(vm.interpret_to(&source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "42\n");
+        assert!(vm.stack.len() < 10);
+
+        let mut vm = VirtualMachine::new().with_depth_limit(100);
+        let source = countdown(printed, "r = call countdown 1\nreturn r");
+        assert_eq!(vm.interpret(&source), Err(TACError::RuntimeError));
+
+        let mut vm = VirtualMachine::new().with_depth_limit(100);
+        // tail calls from the program's own frame keep reusing the frame of the first one
+        let source = countdown("return call countdown 1", "return call countdown 1");
+        assert!(vm.interpret(&source).is_ok());
+        assert!(vm.stack.len() < 10);
+    }
+
+    #[test]
+    fn tail_calls_keep_variables_passed_by_address() {
+        let source = "\
+r = call outer
+println r
+halt
+outer:
+x = 5
+p = &x
+param p
+return call inner 1
+inner:
+v = *params
+return v
+";
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn depth_limit_stops_endless_recursion() {
+        let mut vm = VirtualMachine::new();
+        assert_eq!(
+            vm.interpret("call f\nhalt\nf:\ncall f\nreturn\n"),
+            Err(TACError::RuntimeError)
+        );
+        assert_eq!(vm.frames.len(), DEFAULT_DEPTH_LIMIT + 1);
+
+        let nested = "call f\nhalt\nf:\ncall g\nreturn\ng:\nreturn\n";
+        let mut vm = VirtualMachine::new().with_depth_limit(1);
+        assert_eq!(vm.interpret(nested), Err(TACError::RuntimeError));
+
+        let mut vm = VirtualMachine::new().with_depth_limit(2);
+        assert!(vm.interpret(nested).is_ok());
+    }
+
+    #[test]
+    fn stack_limit_stops_runaway_programs() {
+        let deep_recursion = "call f\nhalt\nf:\ncall f\nreturn\n";
+        let mut vm = VirtualMachine::new()
+            .with_stack_limit(1000)
+            .with_depth_limit(usize::MAX);
+        assert_eq!(vm.interpret(deep_recursion), Err(TACError::RuntimeError));
+        assert!(vm.stack.len() <= 1000);
+
+        let many_values = "param 1\nparam 2\nparam 3\ncall f 3\nhalt\nf:\nx = params\nreturn\n";
+        // params_len, the parameters, x, its address and the value assigned to it
+        let mut vm = VirtualMachine::new().with_stack_limit(6);
+        assert_eq!(vm.interpret(many_values), Err(TACError::RuntimeError));
+
+        let mut vm = VirtualMachine::new().with_stack_limit(7);
+        assert!(vm.interpret(many_values).is_ok());
+    }
+
+    #[test]
+    fn step_limit_stops_infinite_loops() {
+        let mut vm = VirtualMachine::new().with_step_limit(1000);
+
+        assert_eq!(
+            vm.interpret("top:\ngoto top\n"),
+            Err(TACError::RuntimeError)
+        );
+        // stopped as soon as the budget ran out
+        assert_eq!(vm.steps, 1000);
+    }
+
+    #[test]
+    fn timeout_stops_busy_loops() {
+        let timeout = Duration::from_millis(20);
+        let mut vm = VirtualMachine::new().with_timeout(timeout);
+        let start = Instant::now();
+
+        assert_eq!(
+            vm.interpret("top:\ngoto top\n"),
+            Err(TACError::RuntimeError)
+        );
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let mut vm = VirtualMachine::new()
+            .with_timeout(Duration::from_secs(60))
+            .with_timeout_interval(1);
+        assert!(vm.interpret("x = 1\ny = x + 1\n").is_ok());
+    }
+
+    #[test]
+    fn checked_arithmetic_reports_overflows() {
+        let source = "a = 18446744073709551615u64\nb = a + 1u64\n";
+
+        let mut vm = VirtualMachine::new();
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[1], Value::U64(0)));
+
+        let mut vm = VirtualMachine::new().with_arithmetic(ArithmeticMode::Checked);
+        assert_eq!(vm.interpret(source), Err(TACError::RuntimeError));
+
+        for overflowing in &[
+            "a = -9223372036854775808\nb = a - 1\n",
+            "a = 4294967296\nb = a * a\n",
+            "a = -9223372036854775808\nb = a / -1\n",
+            "a = 2u64\nb = a ** 64u64\n",
+        ] {
+            assert_eq!(vm.interpret(overflowing), Err(TACError::RuntimeError));
+        }
+
+        let mut vm = VirtualMachine::new().with_arithmetic(ArithmeticMode::Checked);
+        assert!(vm
+            .interpret("a = 3\nb = a ** 4\nc = b / 2\nd = 1.5 * 2.0\n")
+            .is_ok());
+        assert!(matches!(vm.stack[2], Value::I64(40)));
+    }
+
+    #[test]
+    fn arithmetic_modes_handle_the_same_overflow() {
+        let source =
+            "a = 18446744073709551615u64\nb = a + 1u64\nc = -9223372036854775808\nd = c * 2\n";
+        let run = |mode| {
+            let mut vm = VirtualMachine::new().with_arithmetic(mode);
+            let result = vm.interpret(source);
+            (result, vm.stack)
+        };
+
+        let (result, stack) = run(ArithmeticMode::Wrap);
+        assert!(result.is_ok());
+        assert!(matches!(stack[1], Value::U64(0)));
+        assert!(matches!(stack[3], Value::I64(0)));
+
+        let (result, _) = run(ArithmeticMode::Checked);
+        assert_eq!(result, Err(TACError::RuntimeError));
+
+        let (result, stack) = run(ArithmeticMode::Saturate);
+        assert!(result.is_ok());
+        assert!(matches!(stack[1], Value::U64(u64::MAX)));
+        assert!(matches!(stack[3], Value::I64(i64::MIN)));
+    }
+
+    #[test]
+    fn arithmetic_modes_apply_to_literal_operands() {
+        let source = "x = 9223372036854775807 + 1\n";
+        let run = |mode| {
+            let mut vm = VirtualMachine::new().with_arithmetic(mode);
+            let result = vm.interpret(source);
+            (result, vm.stack)
+        };
+
+        let (result, stack) = run(ArithmeticMode::Wrap);
+        assert!(result.is_ok());
+        assert!(matches!(stack[0], Value::I64(i64::MIN)));
+
+        let (result, _) = run(ArithmeticMode::Checked);
+        assert_eq!(result, Err(TACError::RuntimeError));
+
+        let (result, stack) = run(ArithmeticMode::Saturate);
+        assert!(result.is_ok());
+        assert!(matches!(stack[0], Value::I64(i64::MAX)));
+    }
+
+    #[test]
+    fn incremental_sources_share_variables() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+
+        assert!(vm.interpret_incremental_to("x = 5", &mut out).is_ok());
+        assert_eq!(
+            vm.interpret_incremental_to("y = x +", &mut out),
+            Err(TACError::CompileError)
+        );
+        assert_eq!(
+            vm.interpret_incremental_to("z = 1\ny = z / 0", &mut out),
+            Err(TACError::RuntimeError)
+        );
+        assert!(vm.interpret_incremental_to("x = x * 2", &mut out).is_ok());
+        assert!(vm.interpret_incremental_to("println x", &mut out).is_ok());
+        assert_eq!(
+            vm.interpret_incremental_to("println z", &mut out),
+            Err(TACError::RuntimeError)
+        );
+
+        assert_eq!(String::from_utf8(out).unwrap(), "10\n");
+        assert_eq!(vm.stack.len(), 1);
+    }
+
+    #[test]
+    fn incremental_return_keeps_variables() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+
+        assert!(vm.interpret_incremental_to("x = 5", &mut out).is_ok());
+        assert!(vm.interpret_incremental_to("return", &mut out).is_ok());
+        assert!(vm.interpret_incremental_to("println x", &mut out).is_ok());
+
+        assert_eq!(String::from_utf8(out).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn incremental_compile_errors_leave_the_chunk_untouched() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+
+        assert!(vm.interpret_incremental_to("x = 5", &mut out).is_ok());
+        let code = vm.chunk.code.clone();
+        let (constants, names) = (vm.chunk.constants_len(), vm.chunk.names_len());
+
+        assert_eq!(
+            vm.interpret_incremental_to("y = 7\n.data s = \"hi\"\nz = y +", &mut out),
+            Err(TACError::CompileError)
+        );
+        assert_eq!(vm.chunk.code, code);
+        assert_eq!(vm.chunk.constants_len(), constants);
+        assert_eq!(vm.chunk.names_len(), names);
+        assert!(vm.chunk.data.is_empty());
+
+        assert!(vm
+            .interpret_incremental_to("y = 7\nprintln y", &mut out)
+            .is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "7\n");
+    }
+
+    #[test]
+    fn programs_can_run_one_instruction_at_a_time() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        assert!(vm.load("a = 2\nb = a * 3\n").is_ok());
+
+        let expected = [
+            "[U64(0), Addr(0)]",
+            "[U64(0), Addr(0), I64(2)]",
+            "[I64(2)]",
+            "[I64(2), U64(0), Addr(1)]",
+            "[I64(2), U64(0), Addr(1), I64(2)]",
+            "[I64(2), U64(0), Addr(1), I64(2), I64(3)]",
+            "[I64(2), U64(0), Addr(1), I64(6)]",
+            "[I64(2), I64(6)]",
+        ];
+        for (ip, stack) in expected.iter().enumerate() {
+            assert_eq!(vm.step_to(&mut out), Ok(StepResult::Continue));
+            assert_eq!(vm.ip(), ip + 1);
+            assert_eq!(format!("{:?}", vm.stack()), *stack);
+        }
+
+        assert_eq!(vm.step_to(&mut out), Ok(StepResult::Halted));
+        assert_eq!(vm.current_frame().variables().len(), 2);
+        assert!(vm.current_frame().return_address().is_none());
+    }
+
+    #[test]
+    fn execution_pauses_at_breakpoints() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "x = 1\nprintln x\nx = x + 1\nprintln x\n";
+        vm.add_breakpoint(3);
+        vm.add_breakpoint(4);
+
+        assert!(vm.load(source).is_ok());
+        assert_eq!(vm.resume_to(&mut out), Ok(StepResult::Breakpoint));
+        assert_eq!(vm.chunk().get_line(vm.ip()), 3);
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "1\n");
+
+        // stepping at a breakpoint executes the instruction it paused before
+        assert_eq!(vm.step_to(&mut out), Ok(StepResult::Continue));
+        assert_eq!(vm.resume_to(&mut out), Ok(StepResult::Breakpoint));
+        assert_eq!(vm.chunk().get_line(vm.ip()), 4);
+        assert!(matches!(vm.stack()[0], Value::I64(2)));
+
+        vm.remove_breakpoint(3);
+        assert_eq!(vm.resume_to(&mut out), Ok(StepResult::Halted));
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "1\n2\n");
+
+        assert!(vm.load(source).is_ok());
+        assert_eq!(vm.resume_to(&mut out), Ok(StepResult::Breakpoint));
+        assert_eq!(vm.chunk().get_line(vm.ip()), 4);
+
+        // interpreting ignores breakpoints
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+    }
+
+    #[test]
+    fn casts_convert_between_types() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "x = 7\ny = (f64) x\ny = y / 2.0\nz = (i64) -2.75\nc = (char) 97u64\nprintln y\nprintln z\nprintln c\n";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "3.5\n-2\na\n");
+
+        assert_eq!(
+            VirtualMachine::new().interpret("x = -1\ny = (u64) x\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn bitwise_not_flips_every_bit() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "x = ~0u64\nprintln x\ny = -6\nz = ~y\nprintln z\n";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "18446744073709551615\n5\n");
+
+        assert_eq!(
+            VirtualMachine::new().interpret("x = true\ny = ~x\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn output_is_written_to_the_given_sink() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+
+        assert!(vm
+            .interpret_to("x = 40 + 2\nprint x\nprintln '!'\ninspect x\n", &mut out)
+            .is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "42!\n42i64\n");
+    }
+
+    #[test]
+    fn printw_right_aligns_values() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source =
+            "w = 5u64\nf = -2.5\nprintw 1 w\nprintw f w\nprintw 'x' 3\nprintw 123456 2\nprintln '|'\n";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "    1 -2.5  x123456|\n");
+
+        let mut vm = VirtualMachine::new();
+        for width in &["w = -1\nprintw 1 w\n", "printw 1 2.0\n", "printw 1 true\n"] {
+            assert_eq!(
+                vm.interpret(width),
+                Err(TACError::RuntimeError),
+                "{}",
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn scan_reads_typed_values_from_input() {
+        let input = Cursor::new("42\n 7 \n2.5\ntrue\nz\n");
+        let mut vm = VirtualMachine::new().with_input(input);
+        let source = "a = scan\nb = scan u64\nc = scan f64\nd = scan bool\ne = scan char\n";
+
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(42)));
+        assert!(matches!(vm.stack[1], Value::U64(7)));
+        assert!(matches!(vm.stack[2], Value::F64(v) if v == 2.5));
+        assert!(matches!(vm.stack[3], Value::Bool(true)));
+        assert!(matches!(vm.stack[4], Value::Char('z')));
+
+        assert_eq!(vm.interpret("a = scan\n"), Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn scan_flushes_output_before_reading() {
+        /// Writer that keeps track of how much of the output was flushed.
+        struct Flushes {
+            written: Rc<RefCell<(Vec<u8>, usize)>>,
+        }
+
+        impl Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.written.borrow_mut().0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                let mut written = self.written.borrow_mut();
+                written.1 = written.0.len();
+                Ok(())
+            }
+        }
+
+        let written = Rc::new(RefCell::new((Vec::new(), 0)));
+        let mut out = Flushes {
+            written: Rc::clone(&written),
+        };
+        let mut vm = VirtualMachine::new().with_input(Cursor::new("5\n"));
+
+        assert!(vm
+            .interpret_to("print 'n'\na = scan\nprint a\n", &mut out)
+            .is_ok());
+        assert_eq!(written.borrow().1, 1);
+    }
+
+    #[test]
+    fn scan_rejects_input_of_another_type() {
+        let mut vm = VirtualMachine::new().with_input(Cursor::new("-3\n"));
+
+        assert_eq!(vm.interpret("a = scan u64\n"), Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn double_slash_comments_coexist_with_division() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("a = 6 // hi\nb = 2\nc = a / b\n").is_ok());
+        assert!(matches!(vm.stack[2], Value::I64(3)));
+    }
+
+    #[test]
+    fn greater_equal_compares_integers_and_floats() {
+        let cases = [
+            ("3", "3", true),
+            ("4", "5", false),
+            ("5", "4", true),
+            ("3.0", "3.0", true),
+            ("4.5", "5.5", false),
+            ("5.5", "4.5", true),
+        ];
+
+        for (a, b, expected) in &cases {
+            // both the folded constant expression and the one evaluated at runtime
+            let source = format!(
+                "x = {a} >= {b}\na = {a}\nb = {b}\ny = a >= b\n",
+                a = a,
+                b = b
+            );
+            let mut vm = VirtualMachine::new();
+
+            assert!(vm.interpret(&source).is_ok());
+            assert!(
+                matches!(vm.stack[0], Value::Bool(v) if v == *expected),
+                "{}",
+                source
+            );
+            assert!(
+                matches!(vm.stack[3], Value::Bool(v) if v == *expected),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn less_equal_and_not_equal_compare_integers_and_floats() {
+        let cases = [
+            ("3", "3", true, false),
+            ("4", "5", true, true),
+            ("5", "4", false, true),
+            ("3.0", "3.0", true, false),
+            ("4.5", "5.5", true, true),
+            ("5.5", "4.5", false, true),
+        ];
+
+        for (a, b, less_equal, not_equal) in &cases {
+            let source = format!(
+                "x = {a} <= {b}\ny = {a} != {b}\na = {a}\nb = {b}\nz = a <= b\nw = a != b\n",
+                a = a,
+                b = b
+            );
+            let mut vm = VirtualMachine::new();
+
+            assert!(vm.interpret(&source).is_ok());
+            assert!(
+                matches!(vm.stack[0], Value::Bool(v) if v == *less_equal),
+                "{}",
+                source
+            );
+            assert!(
+                matches!(vm.stack[1], Value::Bool(v) if v == *not_equal),
+                "{}",
+                source
+            );
+            assert!(
+                matches!(vm.stack[4], Value::Bool(v) if v == *less_equal),
+                "{}",
+                source
+            );
+            assert!(
+                matches!(vm.stack[5], Value::Bool(v) if v == *not_equal),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn discarded_intrinsic_leaves_stack_unchanged() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("x = 5\nclamp x 0 10\ny = 1\n").is_ok());
+        assert_eq!(vm.stack.len(), 2);
+
+        assert_eq!(
+            vm.interpret("x = 5\nclamp x 10 0\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn power_operator() {
+        let mut vm = VirtualMachine::new();
+        let source = "a = 3\nb = 4\nx = a ** b\ny = 2.0 ** 3\n";
+
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[2], Value::I64(81)));
+        assert!(matches!(vm.stack[3], Value::F64(v) if v == 8.0));
+
+        assert_eq!(
+            vm.interpret("a = 2\nb = -1\nx = a ** b\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "\
+t = true
+f = false
+a = t && t
+b = t && f
+c = f && t
+d = f || f
+e = f || t
+g = t || f
+println a
+println b
+println c
+println d
+println e
+println g
+if t && f goto end
+ifFalse f || t goto end
+println 1
+end:
+";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "true\nfalse\nfalse\nfalse\ntrue\ntrue\n1\n"
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let mut vm = VirtualMachine::new();
+        let source = "\
+.data a = [1]
+f = false
+t = true
+x = f && a[5]
+y = t || a[5]
+z = f && 1
+";
+
+        // the out of bounds accesses and the non-boolean operand are never evaluated
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[4], Value::Bool(false)));
+        assert!(matches!(vm.stack[5], Value::Bool(true)));
+
+        assert_eq!(
+            vm.interpret("t = true\nx = t && 1\n"),
+            Err(TACError::RuntimeError)
+        );
+        assert_eq!(
+            vm.interpret("f = false\nx = f || a[5]\n.data a = [1]\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn crlf_source_compiles() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("a = 1\r\nb = 2\r\n").is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(1)));
+        assert!(matches!(vm.stack[1], Value::I64(2)));
+    }
+
+    #[test]
+    fn goto_label_at_first_instruction() {
+        let mut vm = VirtualMachine::new();
+        // data declarations emit no code, so `top` labels offset 0
+        let source =
+            ".data count = [0]\ntop:\ncount[0] = count[0] + 1\nc = count[0]\nif c < 3 goto top\n";
+
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(3)));
+    }
+
+    #[test]
+    fn program_with_only_a_label_halts() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm.interpret("only:\n").is_ok());
+        assert!(vm.interpret("only:").is_ok());
+        assert!(vm.interpret("goto end\nend:\n").is_ok());
+    }
+
+    #[test]
+    fn data_arrays_are_laid_out_in_consecutive_slots() {
+        let mut vm = VirtualMachine::new();
+        let source = ".data primes = [2, 3, 5, 7]\nfirst = *primes\n";
+
+        assert!(vm.interpret(source).is_ok());
+
+        let primes: Vec<i64> = vm.stack[..4]
+            .iter()
+            .map(|value| match value {
+                Value::I64(val) => *val,
+                val => panic!("Unexpected value {}", val),
+            })
+            .collect();
+        assert_eq!(primes, vec![2, 3, 5, 7]);
+        assert!(matches!(vm.stack[4], Value::Addr(0)));
+        assert!(matches!(vm.stack[5], Value::I64(2)));
+    }
+
+    #[test]
+    fn array_subscript_reads_element() {
+        let mut vm = VirtualMachine::new();
+        let source = ".data primes = [2, 3, 5, 7]\ni = 3\nx = primes[2]\ny = primes[i] * 2\n";
+
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[6], Value::I64(5)));
+        assert!(matches!(vm.stack[7], Value::I64(14)));
+    }
+
+    #[test]
+    fn array_subscript_writes_element() {
+        let mut vm = VirtualMachine::new();
+        let source = ".data a = [1, 2, 3]\ni = 1\na[i] = 20\na[2] = a[i] + 10\nx = a[2]\n";
+
+        assert!(vm.interpret(source).is_ok());
+        assert!(matches!(vm.stack[0], Value::I64(1)));
+        assert!(matches!(vm.stack[1], Value::I64(20)));
+        assert!(matches!(vm.stack[2], Value::I64(30)));
+        assert!(matches!(vm.stack[5], Value::I64(30)));
+
+        assert_eq!(
+            vm.interpret(".data a = [1, 2]\na[2] = 5\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn dereference_reads_and_writes_through_pointers() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "x = 2\np = &x\ny = *p\n*p = 7\nprint x\n";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "7");
+        assert!(matches!(vm.stack[1], Value::Addr(0)));
+        assert!(matches!(vm.stack[2], Value::I64(2)));
+    }
+
+    #[test]
+    fn array_variable_holds_the_address_of_its_elements() {
+        let mut vm = VirtualMachine::new();
+
+        assert!(vm
+            .interpret(".data a = [4, 5]\np = &a\nq = *p\nx = *a\ny = q[1]\n")
+            .is_ok());
+        assert!(matches!(vm.stack[2], Value::Addr(0)));
+        assert!(matches!(vm.stack[3], Value::Addr(2)));
+        assert!(matches!(vm.stack[4], Value::Addr(0)));
+        assert!(matches!(vm.stack[5], Value::I64(4)));
+        assert!(matches!(vm.stack[6], Value::I64(5)));
+    }
+
+    #[test]
+    fn reference_requires_a_defined_variable() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(vm.interpret("p = &5\n"), Err(TACError::CompileError));
+        assert_eq!(vm.interpret("p = &x\n"), Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn dereference_requires_an_address() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(vm.interpret("x = 1\ny = *x\n"), Err(TACError::RuntimeError));
+        assert_eq!(vm.interpret("x = 1\n*x = 2\n"), Err(TACError::RuntimeError));
+    }
+
+    #[test]
+    fn array_subscript_out_of_bounds() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(
+            vm.interpret(".data a = [1, 2]\nx = a[2]\n"),
+            Err(TACError::RuntimeError)
+        );
+        assert_eq!(
+            vm.interpret(".data a = [1, 2]\ni = -1\nx = a[i]\n"),
+            Err(TACError::RuntimeError)
+        );
+        assert_eq!(
+            vm.interpret("a = 1\nx = a[0]\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn data_strings_are_printed() {
+        let mut vm = VirtualMachine::new();
+        let mut out = vec![];
+        let source = "\
+.data msg = \"hello\\tworld\"
+.data words = [\"a\", \"bc\", \"\"]
+println msg
+x = words[1]
+printw x 4
+same = words[0] == words[0]
+inspect x
+inspect same
+";
+
+        assert!(vm.interpret_to(source, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "hello\tworld\n  bc\"bc\":str\ntrue:bool\n"
+        );
+    }
+
+    #[test]
+    fn strings_outside_data_are_rejected() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(vm.interpret("print \"hi\"\n"), Err(TACError::CompileError));
+        assert_eq!(
+            vm.interpret(".data a = [\"a\", 1]\n"),
+            Err(TACError::CompileError)
+        );
+    }
+
+    #[test]
+    fn data_array_elements_must_share_a_type() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(
+            vm.interpret(".data mixed = [1, 2.5]\n"),
+            Err(TACError::CompileError)
+        );
+    }
+
+    #[test]
+    fn clear_zeroes_whole_array() {
+        let mut vm = VirtualMachine::new();
+        let source = ".data a = [1.5, 2.5, 3.5]\n.data b = [7, 8]\nclear a\n";
+
+        assert!(vm.interpret(source).is_ok());
+
+        assert!(vm.stack[..3]
+            .iter()
+            .all(|value| matches!(value, Value::F64(val) if *val == 0.0)));
+        assert!(matches!(vm.stack[3], Value::I64(7)));
+        assert!(matches!(vm.stack[4], Value::I64(8)));
+    }
+
+    #[test]
+    fn checksum_depends_on_array_contents() {
+        let mut vm = VirtualMachine::new();
+        let source = "\
+.data a = [1, 2, 3]
+.data b = [1, 2, 3]
+.data c = [1, 2, 4]
+x = checksum a
+y = checksum b
+z = checksum c
+";
+
+        assert!(vm.interpret(source).is_ok());
+
+        let (x, y, z) = match (vm.stack[12], vm.stack[13], vm.stack[14]) {
+            (Value::U64(x), Value::U64(y), Value::U64(z)) => (x, y, z),
+            values => panic!("Checksums are not u64 values: {:?}", values),
+        };
+        assert_eq!(x, y);
+        assert_ne!(x, z);
+    }
+
+    #[test]
+    fn clear_requires_an_array() {
+        let mut vm = VirtualMachine::new();
+
+        assert_eq!(
+            vm.interpret("x = 1\nclear x\n"),
+            Err(TACError::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn interrupt_stops_running_program() {
+        let mut vm = VirtualMachine::new();
+        let interrupted = vm.interrupt_handle();
+
+        // loading clears the flag, so it must happen before the flag may be set
+        assert!(vm.load("forever:\ngoto forever\n").is_ok());
+
+        let start = Instant::now();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupted.store(true, Ordering::SeqCst);
+        });
+
+        let result = vm.run(&mut std::io::sink());
+        interrupter.join().unwrap();
+
+        assert_eq!(result, Err(TACError::RuntimeError));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
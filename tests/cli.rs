@@ -0,0 +1,754 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{self, Command, Output, Stdio},
+};
+
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("tac-{}-{}.tac", process::id(), name));
+    fs::write(&path, source).expect("Could not write test script");
+    path
+}
+
+fn run(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = script(name, source);
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg(&path)
+        .args(args)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn bare_comparison_statement_is_rejected() {
+    let output = run("bare_comparison", "a = 1\nb = 2\na == b\n1 < 2\n", &[]);
+    let errors = stderr(&output);
+
+    assert!(errors
+        .contains("[line 3, col 3] Error at '==': The result of this comparison is discarded"));
+    assert!(
+        errors.contains("[line 4, col 3] Error at '<': The result of this comparison is discarded")
+    );
+}
+
+#[test]
+fn missing_labels_are_reported_in_order_of_use() {
+    let source = "goto delta\ngoto alpha\nif true goto charlie\ncall bravo\ngoto alpha\n";
+
+    for _ in 0..5 {
+        let output = run("missing_labels", source, &[]);
+        let errors: Vec<String> = stderr(&output)
+            .lines()
+            .filter(|line| line.contains("Missing label"))
+            .map(String::from)
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                "[line 6, col 1] Error at end: Missing label 'delta', first used in line 1",
+                "[line 6, col 1] Error at end: Missing label 'alpha', first used in line 2",
+                "[line 6, col 1] Error at end: Missing label 'charlie', first used in line 3",
+                "[line 6, col 1] Error at end: Missing label 'bravo', first used in line 4",
+            ]
+        );
+    }
+}
+
+#[test]
+fn arguments_are_echoed() {
+    let source = "\
+n = argc
+i = 0u64
+loop:
+if i == n goto end
+a = arg i
+println a
+i = i + 1u64
+goto loop
+end:
+";
+    let output = run("echo_args", source, &["42", "-7", "2.5", "true", "z"]);
+
+    assert_eq!(stdout(&output), "42\n-7\n2.5\ntrue\nz\n");
+}
+
+#[test]
+fn out_of_range_argument_is_a_runtime_error() {
+    let output = run("arg_out_of_range", "a = arg 1\n", &["only"]);
+
+    assert!(stderr(&output).contains("Argument index 1 is out of range"));
+}
+
+#[test]
+fn escaped_characters_are_printed() {
+    let output = run(
+        "escaped_chars",
+        "print 'a'\nprint '\\t'\nprint '\\''\nprint '\\\\'\nprint '\\n'\n",
+        &[],
+    );
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "a\t'\\\n");
+}
+
+#[test]
+fn hexadecimal_literals_equal_their_decimal_counterparts() {
+    let source = "\
+println 0xFF
+println 0x10u64
+println 0xffi64
+x = 0x7fffffffffffffff == 9223372036854775807
+println x
+";
+    let output = run("hex_literals", source, &[]);
+
+    assert_eq!(stdout(&output), "255\n16\n255\ntrue\n");
+}
+
+#[test]
+fn hexadecimal_literal_with_float_suffix_is_rejected() {
+    let output = run("hex_float_suffix", "println 0xFFf64\n", &[]);
+
+    assert!(stderr(&output).contains("Cannot set f64 suffix to a hexadecimal number"));
+}
+
+#[test]
+fn inspect_prints_values_with_their_types() {
+    let output = run(
+        "inspect",
+        "x = 5\ninspect x\ninspect 'c'\ninspect 2.5\n",
+        &[],
+    );
+
+    assert_eq!(stdout(&output), "5i64\nc:char\n2.5f64\n");
+}
+
+#[test]
+fn binary_literals_equal_their_decimal_counterparts() {
+    let source = "\
+println 0b1010
+println 0B11u64
+x = 0b1111i64 == 15
+println x
+";
+    let output = run("bin_literals", source, &[]);
+
+    assert_eq!(stdout(&output), "10\n3\ntrue\n");
+}
+
+#[test]
+fn binary_literal_with_stray_digit_is_rejected() {
+    let output = run("bin_stray_digit", "println 0b1012\n", &[]);
+
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("Invalid digit '2' in binary number"));
+}
+
+#[test]
+fn file_uri_loads_the_same_program_as_its_path() {
+    let path = script("file_uri", "println 42\n");
+    let uri = format!("file://{}", path.display());
+
+    let from_path = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    let from_uri = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg(&uri)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&from_path), "42\n");
+    assert_eq!(stdout(&from_uri), stdout(&from_path));
+}
+
+#[test]
+fn unsupported_script_scheme_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("https://example.com/prog.tac")
+        .output()
+        .expect("Could not run interpreter");
+
+    assert!(stderr(&output).contains("Unsupported scheme 'https'"));
+}
+
+#[test]
+fn octal_literals_equal_their_decimal_counterparts() {
+    let output = run("oct_literals", "println 0o755\nprintln 0O17u64\n", &[]);
+
+    assert_eq!(stdout(&output), "493\n15\n");
+}
+
+#[test]
+fn octal_literal_with_out_of_range_digit_is_rejected() {
+    let output = run("oct_bad_digit", "x = 1\ny = 0o98\n", &[]);
+
+    assert!(stderr(&output)
+        .contains("[line 2, col 5] Error at '0o98': Invalid digit '9' in octal number"));
+}
+
+#[test]
+fn numeric_type_checks_on_arguments() {
+    let source = "\
+a = arg 0
+n = is_numeric a
+i = is_int a
+f = is_float a
+println n
+println i
+println f
+";
+
+    assert_eq!(
+        stdout(&run("is_numeric_int", source, &["7"])),
+        "true\ntrue\nfalse\n"
+    );
+    assert_eq!(
+        stdout(&run("is_numeric_float", source, &["2.5"])),
+        "true\nfalse\ntrue\n"
+    );
+    assert_eq!(
+        stdout(&run("is_numeric_char", source, &["x"])),
+        "false\nfalse\nfalse\n"
+    );
+}
+
+#[test]
+fn scientific_notation_float_literals() {
+    let source = "\
+println 1e3
+println 2.5E-2
+println 6.022e+23
+println 1e3f64
+";
+    let output = run("scientific", source, &[]);
+
+    assert_eq!(
+        stdout(&output),
+        "1000\n0.025\n602200000000000000000000\n1000\n"
+    );
+}
+
+#[test]
+fn exponent_without_digits_is_rejected() {
+    let output = run("bare_exponent", "println 1e\n", &[]);
+
+    assert!(stderr(&output).contains("Missing digits in the exponent of float number"));
+}
+
+#[test]
+fn size_reports_chunk_counts_without_running() {
+    let source = "\
+.data a = [1, 2]
+x = 1
+y = x + 2
+println y
+";
+    let path = script("size", source);
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--size")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    let report = stdout(&output);
+    let lines: Vec<&str> = report.lines().collect();
+
+    assert_eq!(
+        lines[..4],
+        [
+            "Instructions: 11",
+            "Constants: 2",
+            "Names: 3",
+            "Data arrays: 1"
+        ]
+    );
+    assert!(lines[4].starts_with("Size: "));
+    assert_eq!(lines.len(), 5);
+}
+
+#[test]
+fn clamp_restricts_values_to_range() {
+    let source = "\
+n = -5
+a = clamp n 0 10
+b = clamp 5 0 10
+c = clamp 15 0 10
+println a
+println b
+println c
+";
+    let output = run("clamp", source, &[]);
+
+    assert_eq!(stdout(&output), "0\n5\n10\n");
+}
+
+#[test]
+fn chained_assignment_sets_every_target() {
+    let source = "\
+a = b = 5
+x = y = z = a + 1
+println a
+println b
+println x
+println y
+println z
+";
+    let output = run("chained_assignment", source, &[]);
+
+    assert_eq!(stdout(&output), "5\n5\n6\n6\n6\n");
+
+    let source = "\
+param 2
+x = y = call f 1
+println x
+println y
+halt
+f:
+n = params
+q = r = n * 10
+return q + r
+";
+    let output = run("chained_call_assignment", source, &[]);
+
+    assert_eq!(stdout(&output), "40\n40\n");
+}
+
+#[test]
+fn chained_assignment_to_dereference_is_rejected() {
+    let output = run("chained_dereference", "p = 1\n*p = b = 5\n", &[]);
+
+    assert!(stderr(&output).contains("Chained assignments only support plain variables as targets"));
+
+    let output = run(
+        "chained_later_dereference",
+        "x = 1\np = &x\na = *p = 3\n",
+        &[],
+    );
+
+    assert!(stderr(&output).contains(
+        "[line 3, col 8] Error at '=': Chained assignments only support plain variables as targets"
+    ));
+}
+
+#[test]
+fn chained_assignment_to_array_element_is_rejected() {
+    let output = run(
+        "chained_element",
+        ".data arr = [1, 2]\na = arr[0] = 7\n",
+        &[],
+    );
+
+    assert!(stderr(&output).contains(
+        "[line 2, col 12] Error at '=': Chained assignments only support plain variables as targets"
+    ));
+}
+
+#[test]
+fn unicode_escape_in_char_is_printed() {
+    let output = run(
+        "unicode_char",
+        "println '\\u{41}'\nprintln '\\u{e9}'\n",
+        &[],
+    );
+
+    assert_eq!(stdout(&output), "A\né\n");
+}
+
+#[test]
+fn trailing_operator_names_the_operator() {
+    let output = run("trailing_operator", "a = 1\nx = a +\ny = -\n", &[]);
+    let errors = stderr(&output);
+
+    assert!(errors.contains("[line 2, col 7] Error at '+': Expected operand after '+'"));
+    assert!(errors.contains("[line 3, col 5] Error at '-': Expected operand after '-'"));
+}
+
+#[test]
+fn leading_dot_float_literals() {
+    let output = run("leading_dot", "x = .5 + 1.0\nprintln x\ninspect .25\n", &[]);
+
+    assert_eq!(stdout(&output), "1.5\n0.25f64\n");
+}
+
+#[test]
+fn format_prints_canonical_program_without_running_it() {
+    let path = script("format", "x=1   # one\n\n\n  println   x\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--format")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&output), "x = 1 # one\n\nprintln x\n");
+}
+
+#[test]
+fn unexpected_character_is_reported() {
+    let output = run("unexpected_character", "x = 1\ny = x @ 2\n", &[]);
+
+    assert!(stderr(&output).contains("[line 2, col 7] Error: Unexpected character '@'"));
+}
+
+#[test]
+fn carets_line_up_under_tab_indented_tokens() {
+    let output = run("tab_caret", "x = 1\n\ty = x @ 2\n", &[]);
+
+    assert!(stderr(&output).contains(
+        "[line 2, col 15] Error: Unexpected character '@'\n            y = x @ 2\n                  ^\n"
+    ));
+}
+
+#[test]
+fn scan_must_be_assigned() {
+    let output = run("bare_scan", "scan\nx = scan\n", &[]);
+    let errors = stderr(&output);
+
+    assert!(errors.contains(
+        "[line 1, col 1] Error at 'scan': Return value of scan must be assigned to a variable, as in 'x = scan'"
+    ));
+    assert_eq!(errors.matches("must be assigned").count(), 1);
+}
+
+#[test]
+fn shebang_line_is_ignored() {
+    let output = run("shebang", "#!/usr/bin/env tac\nx = 1\ny = x + true\n", &[]);
+
+    assert!(stderr(&output).contains("[line 3] in script"));
+
+    let output = run("shebang_print", "#!/usr/bin/env tac\nprintln 7\n", &[]);
+    assert_eq!(stdout(&output), "7\n");
+}
+
+#[test]
+fn modulo_program() {
+    let source = include_str!("../programs/modulo.tac");
+    let output = run("modulo", source, &[]);
+
+    assert_eq!(stdout(&output), "1\n1\n1\n1.5\n-1\n");
+    assert!(stderr(&output).contains("Division by 0\n[line 8] in script"));
+}
+
+#[test]
+fn runtime_errors_report_the_line_of_the_failing_instruction() {
+    let output = run("division_line", "x = 0\ny = 1 / x\nprintln y\n", &[]);
+    assert!(stderr(&output).contains("Division by 0\n[line 2] in script"));
+
+    // the condition is the last instruction of its line
+    let output = run(
+        "condition_line",
+        "x = 1\nif x goto end\nprintln x\nend:\n",
+        &[],
+    );
+    assert!(stderr(&output).contains("'bool' required.\n[line 2] in script"));
+}
+
+#[test]
+fn max_steps_stops_runaway_programs() {
+    let path = script("max_steps", "println steps_left\ntop:\ngoto top\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .args(["--max-steps", "50"])
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&output), "49\n");
+    assert!(stderr(&output).contains("Step limit of 50 instructions exceeded"));
+}
+
+#[test]
+fn emit_c_prints_a_c_program_without_running_it() {
+    let path = script("emit_c", "x = 1\nprintln x\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--emit-c")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    let c = stdout(&output);
+    assert!(c.starts_with("#include"));
+    assert!(c.contains("int main(void) {"));
+    assert!(!c.lines().any(|line| line == "1"));
+}
+
+#[test]
+fn scan_reads_from_stdin() {
+    let path = script(
+        "scan_stdin",
+        "x = scan\ny = x * 2\nprintln y\nc = scan char\nprintln c\n",
+    );
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Could not run interpreter");
+    child.stdin.take().unwrap().write_all(b"21\nq\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&output), "42\nq\n");
+}
+
+#[test]
+fn endless_recursion_is_warned_about() {
+    let source = "\
+call forever
+call countdown, 0u64
+halt
+forever:
+x = 1
+call forever
+return
+countdown:
+if true goto done
+call countdown, 0u64
+done:
+return
+";
+    let path = script("endless_recursion", source);
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--size")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+    let errors = stderr(&output);
+
+    assert!(errors
+        .contains("Warning: Subroutine 'forever' calls itself on every path before returning"));
+    assert!(!errors.contains("'countdown'"));
+}
+
+#[test]
+fn recursive_factorial_program() {
+    let output = run("factorial", include_str!("../programs/factorial.tac"), &[]);
+
+    assert_eq!(stdout(&output), "3628800\n");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn call_count_follows_label_with_or_without_comma() {
+    let source = "\
+param 1
+param 2
+call f, 2u64
+param 3
+call f 1
+call g
+halt
+f:
+println params
+return
+g:
+println params_len
+return
+";
+    let output = run("call_forms", source, &[]);
+
+    assert_eq!(stdout(&output), "2\n3\n0\n");
+}
+
+#[test]
+fn subroutines_return_to_their_caller() {
+    let source = "\
+call greet
+println 2
+return
+greet:
+println 1
+return
+";
+    let output = run("subroutine_return", source, &[]);
+
+    assert_eq!(stdout(&output), "1\n2\n");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn overflowing_constant_expression_is_warned_about() {
+    let output = run(
+        "constant_overflow",
+        "x = 18446744073709551615u64 + 1u64\nprintln x\ny = 2 + 3\n",
+        &[],
+    );
+
+    assert_eq!(stdout(&output), "0\n");
+    assert_eq!(
+        stderr(&output),
+        "Warning: [line 1] Constant expression overflows, its result does not fit in u64\n"
+    );
+}
+
+#[test]
+fn unused_labels_are_warned_about() {
+    let source = "\
+x = 1
+goto used
+unused:
+x = 2
+used:
+orphan:
+print x
+";
+    let output = run("unused_labels", source, &[]);
+
+    assert_eq!(stdout(&output), "1");
+    assert_eq!(
+        stderr(&output),
+        "Warning: [line 3] Label 'unused' is defined but never used\n\
+         Warning: [line 6] Label 'orphan' is defined but never used\n"
+    );
+}
+
+#[test]
+fn typecheck_stops_ill_typed_programs_before_running() {
+    let path = script("typecheck", "println 1\nx = true\ny = x + 1\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--typecheck")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with(
+        "[line 3] Type error: Operator '+' not supported between values of type 'bool' and 'i64'\n"
+    ));
+}
+
+#[test]
+fn unreachable_code_is_warned_about() {
+    let source = "\
+x = 1
+goto skip
+x = 2
+println x
+skip:
+println x
+halt
+x = 3
+goto skip
+";
+    let output = run("unreachable", source, &[]);
+
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(
+        stderr(&output),
+        "Warning: [line 3] Unreachable code, it follows a goto, halt or return and has no label\n\
+         Warning: [line 8] Unreachable code, it follows a goto, halt or return and has no label\n"
+    );
+}
+
+#[test]
+fn reading_unassigned_variable_is_rejected() {
+    let output = run("unassigned_read", "x = 1\ny = x + z\nz = 2\n", &[]);
+
+    assert!(stderr(&output)
+        .contains("[line 2, col 9] Error at 'z': Variable 'z' is read before being assigned"));
+}
+
+#[test]
+fn misspelled_variable_gets_a_suggestion() {
+    let output = run("misspelled", "counter = 1\ntotal = countr + 1\n", &[]);
+
+    assert!(stderr(&output).contains(
+        "Error at 'countr': Variable 'countr' is read before being assigned, did you mean 'counter'?"
+    ));
+}
+
+#[test]
+fn smallest_i64_literal_is_accepted() {
+    let source = "x = -9223372036854775808\nprintln x\n.data d = [-9223372036854775808]\ny = d[0]\nprintln y\n";
+    let output = run("smallest_i64", source, &[]);
+
+    assert_eq!(
+        stdout(&output),
+        "-9223372036854775808\n-9223372036854775808\n"
+    );
+}
+
+#[test]
+fn short_circuited_operand_is_not_evaluated() {
+    let source = "\
+.data a = [1]
+f = false
+ok = f && a[9]
+println ok
+if ok || f goto end
+println 7
+end:
+";
+    let output = run("short_circuit", source, &[]);
+
+    assert_eq!(stdout(&output), "false\n7\n");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn time_flag_reports_to_stderr() {
+    let path = script("time", "x = 1 + 2\nprintln x\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .arg("--time")
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    assert_eq!(stdout(&output), "3\n");
+
+    let errors = stderr(&output);
+    let summary = errors
+        .strip_prefix("Executed 6 instructions in ")
+        .and_then(|rest| rest.strip_suffix(" ms\n"))
+        .expect("Missing timing summary");
+    assert!(summary.parse::<f64>().is_ok());
+    assert_eq!(summary.split('.').nth(1).map(str::len), Some(3));
+}
+
+#[test]
+fn reports_are_skipped_when_compilation_fails() {
+    let path = script("reports_compile_error", "x = 1 +\nprintln x\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .args(["--coverage", "--time"])
+        .arg(&path)
+        .output()
+        .expect("Could not run interpreter");
+    fs::remove_file(path).expect("Could not remove test script");
+
+    let errors = stderr(&output);
+    assert!(errors.contains("Expected operand after '+'"));
+    assert!(!errors.contains("Coverage"));
+    assert!(!errors.contains("Executed"));
+}
+
+#[test]
+fn strings_in_errors_show_their_text() {
+    let output = run(
+        "string_error",
+        ".data count = \"two\"\ncall f count\nhalt\nf:\nreturn\n",
+        &[],
+    );
+
+    assert!(stderr(&output)
+        .contains("Parameter count must be a non-negative integer but found 'two' of type str"));
+}
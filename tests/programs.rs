@@ -0,0 +1,68 @@
+use std::{ffi::OsStr, fs, path::Path, process::Command};
+
+/// Instructions any of the example programs may execute. Enough for all of them while making an
+/// accidental infinite loop fail quickly instead of hanging.
+const MAX_STEPS: &str = "1000000";
+
+fn run_bounded(path: &Path) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_tac"))
+        .args(["--max-steps", MAX_STEPS])
+        .arg(path)
+        .output()
+        .expect("Could not run interpreter");
+
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn programs_finish_within_limits() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("programs");
+
+    for entry in fs::read_dir(dir).expect("Could not list programs") {
+        let path = entry.expect("Could not read program entry").path();
+        if path.extension() != Some(OsStr::new("tac")) {
+            continue;
+        }
+
+        let (_, stderr) = run_bounded(&path);
+        assert!(
+            !stderr.contains("Step limit"),
+            "{} exceeded the step limit",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn programs_print_expected_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("programs");
+
+    for entry in fs::read_dir(dir).expect("Could not list programs") {
+        let path = entry.expect("Could not read program entry").path();
+        if path.extension() != Some(OsStr::new("tac")) {
+            continue;
+        }
+
+        let expected = fs::read_to_string(path.with_extension("out"))
+            .unwrap_or_else(|_| panic!("{} has no expected output", path.display()));
+        let (stdout, _) = run_bounded(&path);
+        assert_eq!(
+            stdout,
+            expected,
+            "{} printed unexpected output",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn bounded_program_completes_within_budget() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("programs/bounded_loop.tac");
+    let (stdout, stderr) = run_bounded(&path);
+
+    assert_eq!(stdout, "500500\n");
+    assert_eq!(stderr, "");
+}